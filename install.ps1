@@ -45,10 +45,13 @@ try {
     Invoke-WebRequest -Uri $DownloadUrl -OutFile $ZipPath
     Expand-Archive -Path $ZipPath -DestinationPath $TempDir -Force
 
+    # Pass the key through the environment so it doesn't show up in process listings
+    $env:HACKATIME_API_KEY = $ApiKey
+
     if ($ApiUrl) {
-        & (Join-Path $TempDir $BinaryName) --key $ApiKey --api-url $ApiUrl
+        & (Join-Path $TempDir $BinaryName) --api-url $ApiUrl
     } else {
-        & (Join-Path $TempDir $BinaryName) --key $ApiKey
+        & (Join-Path $TempDir $BinaryName)
     }
 }
 finally {
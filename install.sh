@@ -61,8 +61,11 @@ curl -sL "$DOWNLOAD_URL" -o "$TEMP_DIR/$ASSET_NAME"
 tar -xzf "$TEMP_DIR/$ASSET_NAME" -C "$TEMP_DIR"
 chmod +x "$TEMP_DIR/$BINARY_NAME"
 
+# Pass the key through the environment so it doesn't show up in `ps`
+export HACKATIME_API_KEY="$API_KEY"
+
 if [ -n "$API_URL" ]; then
-    "$TEMP_DIR/$BINARY_NAME" --key "$API_KEY" --api-url "$API_URL"
+    "$TEMP_DIR/$BINARY_NAME" --api-url "$API_URL"
 else
-    "$TEMP_DIR/$BINARY_NAME" --key "$API_KEY"
+    "$TEMP_DIR/$BINARY_NAME"
 fi
@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use color_eyre::{Result, eyre::eyre};
+use serde::Deserialize;
+
+use crate::config::PrivacyOptions;
+use crate::output::{self, ErrorCode};
+use crate::{SetupArgs, SetupMode};
+
+/// A pre-filled set of answers for `--answers`, in TOML or JSON. Every setup
+/// flag has a matching key; flags given on the command line win.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Answers {
+    api_url: Option<String>,
+    yes: Option<bool>,
+    mode: Option<SetupMode>,
+    hide_branch_names: Option<bool>,
+    anonymize_hostname: Option<bool>,
+    replace_config: Option<bool>,
+    editors: Option<Vec<String>>,
+    all_editors: Option<bool>,
+    no_editors: Option<bool>,
+    skip_wakatime_cli: Option<bool>,
+    skip_test_heartbeat: Option<bool>,
+    from_bundle: Option<PathBuf>,
+
+    /// Patterns for wakatime-cli's `exclude` setting
+    exclude: Vec<String>,
+
+    /// Extra keys to write into `[settings]`. These win over the defaults
+    /// setup would otherwise keep from an existing config.
+    settings: BTreeMap<String, SettingValue>,
+}
+
+/// Keys setup writes from its own answers, which `[settings]` can't override:
+/// the key is checked against `api_url`, not whatever the table says.
+const SETUP_KEYS: &[&str] = &["api_url", "api_key", "hostname", "hide_branch_names"];
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SettingValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl fmt::Display for SettingValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingValue::Bool(b) => write!(f, "{b}"),
+            SettingValue::Integer(i) => write!(f, "{i}"),
+            SettingValue::Float(x) => write!(f, "{x}"),
+            SettingValue::Text(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+impl Answers {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| eyre!("Failed to read {}: {}", path.display(), e))?;
+
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+
+        let invalid = |e: &dyn fmt::Display| {
+            output::coded(
+                ErrorCode::AnswersInvalid,
+                format!("Invalid answers file {}: {}", path.display(), e),
+            )
+        };
+
+        let answers: Self = if is_json {
+            serde_json::from_str(&contents).map_err(|e| invalid(&e))?
+        } else {
+            toml::from_str(&contents).map_err(|e| invalid(&e))?
+        };
+
+        if let Some(key) = SETUP_KEYS
+            .iter()
+            .find(|key| answers.settings.contains_key(**key))
+        {
+            let answer = match *key {
+                "api_url" => "use the top-level api_url answer",
+                "api_key" => "pass the key with --key-file, --key-stdin or HACKATIME_API_KEY",
+                "hostname" => "use anonymize_hostname",
+                _ => "use the top-level hide_branch_names answer",
+            };
+            return Err(invalid(&format!(
+                "{key} can't be set in [settings], {answer} instead"
+            )));
+        }
+
+        Ok(answers)
+    }
+
+    /// Fills in every setup flag that wasn't given on the command line.
+    pub fn apply_to(&self, args: &mut SetupArgs) {
+        if args.api_url.is_none() {
+            args.api_url.clone_from(&self.api_url);
+        }
+        if args.mode.is_none() {
+            args.mode = self.mode;
+        }
+        if args.from_bundle.is_none() {
+            args.from_bundle.clone_from(&self.from_bundle);
+        }
+
+        args.yes |= self.yes.unwrap_or(false);
+        args.replace_config |= self.replace_config.unwrap_or(false);
+        args.skip_wakatime_cli |= self.skip_wakatime_cli.unwrap_or(false);
+        args.skip_test_heartbeat |= self.skip_test_heartbeat.unwrap_or(false);
+
+        let editors_given = args.editors.is_some() || args.all_editors || args.no_editors;
+        if !editors_given {
+            args.editors.clone_from(&self.editors);
+            args.all_editors = self.all_editors.unwrap_or(false);
+            args.no_editors = self.no_editors.unwrap_or(false);
+        }
+    }
+
+    /// The privacy answers. A flag on the command line means yes; otherwise
+    /// the file's answer is used, so it can say no as well as yes.
+    pub fn privacy(&self, args: &SetupArgs) -> PrivacyOptions {
+        PrivacyOptions {
+            hide_branch_names: args
+                .hide_branch_names
+                .then_some(true)
+                .or(self.hide_branch_names),
+            anonymize_hostname: args
+                .anonymize_hostname
+                .then_some(true)
+                .or(self.anonymize_hostname),
+        }
+    }
+
+    /// Extra `[settings]` keys to write, including `exclude`.
+    pub fn settings(&self) -> Vec<(String, String)> {
+        let mut settings: Vec<(String, String)> = self
+            .settings
+            .iter()
+            .map(|(key, value)| (key.clone(), value.to_string()))
+            .collect();
+
+        if !self.exclude.is_empty() {
+            // wakatime-cli reads one pattern per indented line
+            let patterns: String = self
+                .exclude
+                .iter()
+                .map(|pattern| format!("\n    {pattern}"))
+                .collect();
+            settings.push(("exclude".to_string(), patterns));
+        }
+
+        settings
+    }
+}
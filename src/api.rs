@@ -0,0 +1,137 @@
+use std::fmt;
+
+use chrono::Local;
+use reqwest::StatusCode;
+use reqwest::blocking::Response;
+use serde::{Deserialize, Serialize};
+
+use crate::http;
+
+/// The account an API key belongs to, as reported by `users/current`.
+/// Servers fill in different fields, so all of them are optional.
+#[derive(Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct Account {
+    pub username: Option<String>,
+    pub display_name: Option<String>,
+    pub email: Option<String>,
+}
+
+impl Account {
+    /// The friendliest name we have for the account.
+    pub fn label(&self) -> &str {
+        self.display_name
+            .as_deref()
+            .or(self.username.as_deref())
+            .or(self.email.as_deref())
+            .unwrap_or("your account")
+    }
+}
+
+#[derive(Deserialize)]
+struct CurrentUserResponse {
+    data: Account,
+}
+
+#[derive(Debug)]
+pub enum ApiError {
+    /// The server rejected the API key (401 or 403).
+    Unauthorized(StatusCode),
+    /// Any other non-2xx response.
+    Status(StatusCode),
+    /// The server couldn't be reached.
+    Network(reqwest::Error),
+    /// A 2xx response that isn't what the API sends, e.g. an HTML page.
+    Unreadable(reqwest::Error),
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Unauthorized(status) => {
+                write!(f, "the server rejected the API key ({status})")
+            }
+            ApiError::Status(status) => write!(f, "the server answered with HTTP {status}"),
+            ApiError::Network(e) => write!(f, "{e}"),
+            ApiError::Unreadable(e) => {
+                write!(f, "the response isn't WakaTime API JSON ({e})")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Looks up the account `api_key` belongs to, which also proves the server
+/// accepts it.
+pub fn current_user(api_url: &str, api_key: &str) -> Result<Account, ApiError> {
+    get(api_url, api_key, "users/current", &[])?
+        .json::<CurrentUserResponse>()
+        .map(|r| r.data)
+        .map_err(ApiError::Unreadable)
+}
+
+/// Time tracked today, in total and per machine.
+#[derive(Deserialize, Serialize)]
+pub struct TodaySummary {
+    pub grand_total: Duration,
+    #[serde(default)]
+    pub machines: Vec<MachineTime>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Duration {
+    pub text: String,
+    pub total_seconds: f64,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct MachineTime {
+    pub name: String,
+    pub text: String,
+    pub total_seconds: f64,
+}
+
+#[derive(Deserialize)]
+struct SummariesResponse {
+    data: Vec<TodaySummary>,
+}
+
+/// Fetches today's summary, by the date on this computer.
+pub fn today(api_url: &str, api_key: &str) -> Result<Option<TodaySummary>, ApiError> {
+    let date = Local::now().format("%Y-%m-%d").to_string();
+    let response = get(
+        api_url,
+        api_key,
+        "users/current/summaries",
+        &[("start", &date), ("end", &date)],
+    )?;
+    Ok(response
+        .json::<SummariesResponse>()
+        .map_err(ApiError::Unreadable)?
+        .data
+        .pop())
+}
+
+fn get(
+    api_url: &str,
+    api_key: &str,
+    path: &str,
+    query: &[(&str, &str)],
+) -> Result<Response, ApiError> {
+    let request = http::client()
+        .map_err(ApiError::Network)?
+        .get(format!("{api_url}/{path}"))
+        .query(query)
+        .bearer_auth(api_key);
+    let response = http::send(request).map_err(ApiError::Network)?;
+
+    let status = response.status();
+    if matches!(status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) {
+        return Err(ApiError::Unauthorized(status));
+    }
+    if !status.is_success() {
+        return Err(ApiError::Status(status));
+    }
+    Ok(response)
+}
@@ -0,0 +1,258 @@
+use std::fs;
+use std::io::BufRead;
+use std::path::Path;
+
+use color_eyre::{Result, eyre::eyre};
+use uuid::Uuid;
+
+use crate::output::{self, ErrorCode};
+use crate::{DEFAULT_API_URL, SetupArgs, prompt};
+
+/// The key formats servers hand out. Hackatime is strict so typos are caught
+/// before anything is written; other servers get the final say through
+/// `users/current`.
+#[derive(Debug, PartialEq)]
+enum KeyFormat {
+    /// A UUIDv4
+    Hackatime,
+    /// A UUID, optionally prefixed with `waka_`
+    WakaTime,
+    /// Anything without whitespace, e.g. self-hosted Wakapi
+    Any,
+}
+
+impl KeyFormat {
+    fn for_server(api_url: &str) -> Self {
+        let host = api_url
+            .split("://")
+            .nth(1)
+            .unwrap_or(api_url)
+            .split(['/', ':'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        if api_url == DEFAULT_API_URL
+            || host == "hackatime.hackclub.com"
+            || api_url.contains("/api/hackatime/")
+        {
+            KeyFormat::Hackatime
+        } else if host == "wakatime.com" || host.ends_with(".wakatime.com") {
+            KeyFormat::WakaTime
+        } else {
+            KeyFormat::Any
+        }
+    }
+}
+
+/// Checks `key` looks like a key for the server at `api_url`.
+pub fn validate_api_key(api_url: &str, key: &str) -> Result<(), String> {
+    match KeyFormat::for_server(api_url) {
+        KeyFormat::Hackatime => {
+            if key.starts_with("waka_") {
+                return Err("That looks like a WakaTime.com key. Hackatime has its own keys: copy yours from your Hackatime settings page, or pass --api-url wakatime to use WakaTime instead."
+                    .to_string());
+            }
+            let uuid = Uuid::try_parse(key).map_err(
+                |_| "API key must be a valid UUID. Did you copy the command incorrectly?",
+            )?;
+            if uuid.get_version_num() != 4 {
+                return Err("API key must be a valid UUIDv4".to_string());
+            }
+        }
+        KeyFormat::WakaTime => {
+            Uuid::try_parse(key.strip_prefix("waka_").unwrap_or(key)).map_err(|_| {
+                "WakaTime keys look like waka_ followed by a UUID. Copy yours from wakatime.com/settings/api-key."
+            })?;
+        }
+        KeyFormat::Any => {
+            if key.is_empty() || key.chars().any(char::is_whitespace) {
+                return Err("API key can't be empty or contain spaces".to_string());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// When the server rejects a key that looks like it's meant for a different
+/// service, says which one.
+pub fn explain_rejection(api_url: &str, key: &str) -> Option<&'static str> {
+    let format = KeyFormat::for_server(api_url);
+    if key.starts_with("waka_") && !matches!(format, KeyFormat::WakaTime) {
+        Some("It looks like a WakaTime.com key: pass --api-url wakatime to use WakaTime instead.")
+    } else if matches!(format, KeyFormat::WakaTime)
+        && Uuid::try_parse(key).is_ok_and(|uuid| uuid.get_version_num() == 4)
+    {
+        Some("It may be a Hackatime key: leave out --api-url to use Hackatime instead.")
+    } else {
+        None
+    }
+}
+
+/// Hides all but the last four characters of a key for display.
+pub fn mask_api_key(key: &str) -> String {
+    let visible: String = key
+        .chars()
+        .rev()
+        .take(4)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    format!(
+        "{}{}",
+        "•".repeat(key.chars().count().saturating_sub(4).min(8)),
+        visible
+    )
+}
+
+/// Read when no key is given on the command line.
+const API_KEY_ENV: &str = "HACKATIME_API_KEY";
+
+/// Finds the API key from `--key`, `--key-stdin` or `--key-file`, then
+/// `HACKATIME_API_KEY`, falling back to a hidden prompt. The environment
+/// comes last so an explicit source always overrides it.
+pub fn resolve_api_key(args: &SetupArgs, api_url: &str) -> Result<String> {
+    let from_env = std::env::var(API_KEY_ENV)
+        .ok()
+        .filter(|key| !key.trim().is_empty());
+
+    let key = if let Some(key) = &args.key {
+        key.trim().to_string()
+    } else if args.key_stdin {
+        read_key_from_stdin()?
+    } else if let Some(path) = &args.key_file {
+        read_key_from_file(path)?
+    } else if let Some(key) = from_env {
+        key.trim().to_string()
+    } else if !args.yes && prompt::can_prompt() {
+        return prompt::password("Paste your Hackatime API key", |key| {
+            validate_api_key(api_url, key)
+        });
+    } else {
+        return Err(output::coded(
+            ErrorCode::MissingApiKey,
+            "No API key given. Pass --key-stdin, --key-file or set HACKATIME_API_KEY.",
+        ));
+    };
+
+    validate_api_key(api_url, &key).map_err(|e| output::coded(ErrorCode::InvalidApiKey, e))?;
+    Ok(key)
+}
+
+fn read_key_from_stdin() -> Result<String> {
+    let mut line = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|e| eyre!("Failed to read API key from stdin: {}", e))?;
+    Ok(line.trim().to_string())
+}
+
+fn read_key_from_file(path: &Path) -> Result<String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| eyre!("Failed to read API key from {}: {}", path.display(), e))?;
+    Ok(contents.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::WAKATIME_API_URL;
+
+    const HACKATIME_KEY: &str = "7b9d1c1e-3f4a-4b2c-9d8e-1a2b3c4d5e6f";
+    const WAKATIME_KEY: &str = "waka_7b9d1c1e-3f4a-4b2c-9d8e-1a2b3c4d5e6f";
+    const WAKAPI_URL: &str = "https://wakapi.example.com/api";
+
+    #[test]
+    fn picks_the_key_format_by_server() {
+        let cases = [
+            (DEFAULT_API_URL, KeyFormat::Hackatime),
+            (
+                "https://HACKATIME.hackclub.com/api/v1",
+                KeyFormat::Hackatime,
+            ),
+            (
+                "http://localhost:3000/api/hackatime/v1",
+                KeyFormat::Hackatime,
+            ),
+            (WAKATIME_API_URL, KeyFormat::WakaTime),
+            ("https://WakaTime.com/api/v1", KeyFormat::WakaTime),
+            ("https://evilwakatime.com/api/v1", KeyFormat::Any),
+            ("https://wakatime.com.example.com/api/v1", KeyFormat::Any),
+            (WAKAPI_URL, KeyFormat::Any),
+        ];
+        for (api_url, expected) in cases {
+            assert_eq!(KeyFormat::for_server(api_url), expected, "{api_url}");
+        }
+    }
+
+    #[test]
+    fn validates_keys_for_each_server() {
+        // (server, key, error containing, or None if valid)
+        let cases = [
+            (DEFAULT_API_URL, HACKATIME_KEY, None),
+            (DEFAULT_API_URL, &HACKATIME_KEY.to_uppercase(), None),
+            (
+                DEFAULT_API_URL,
+                WAKATIME_KEY,
+                Some("looks like a WakaTime.com key"),
+            ),
+            (
+                DEFAULT_API_URL,
+                "7b9d1c1e-3f4a-1b2c-9d8e-1a2b3c4d5e6f",
+                Some("valid UUIDv4"),
+            ),
+            (DEFAULT_API_URL, "not-a-key", Some("valid UUID")),
+            (
+                DEFAULT_API_URL,
+                &format!(" {HACKATIME_KEY}"),
+                Some("valid UUID"),
+            ),
+            (WAKATIME_API_URL, WAKATIME_KEY, None),
+            (WAKATIME_API_URL, HACKATIME_KEY, None),
+            (
+                WAKATIME_API_URL,
+                "waka_not-a-uuid",
+                Some("waka_ followed by a UUID"),
+            ),
+            (WAKAPI_URL, "anything-without-spaces", None),
+            (WAKAPI_URL, WAKATIME_KEY, None),
+            (WAKAPI_URL, "", Some("can't be empty")),
+            (WAKAPI_URL, "has a space", Some("contain spaces")),
+            (WAKAPI_URL, "tab\there", Some("contain spaces")),
+        ];
+        for (api_url, key, expected) in cases {
+            let result = validate_api_key(api_url, key);
+            match expected {
+                None => assert!(result.is_ok(), "{api_url} rejected {key:?}: {result:?}"),
+                Some(message) => {
+                    let error = result.expect_err(&format!("{api_url} accepted {key:?}"));
+                    assert!(error.contains(message), "{key:?}: {error}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn explains_keys_meant_for_the_other_service() {
+        let cases = [
+            (DEFAULT_API_URL, WAKATIME_KEY, Some("--api-url wakatime")),
+            (WAKAPI_URL, WAKATIME_KEY, Some("--api-url wakatime")),
+            (WAKATIME_API_URL, HACKATIME_KEY, Some("leave out --api-url")),
+            (DEFAULT_API_URL, HACKATIME_KEY, None),
+            (WAKATIME_API_URL, WAKATIME_KEY, None),
+            (WAKAPI_URL, HACKATIME_KEY, None),
+        ];
+        for (api_url, key, expected) in cases {
+            let explanation = explain_rejection(api_url, key);
+            match expected {
+                None => assert_eq!(explanation, None, "{api_url} {key}"),
+                Some(hint) => assert!(
+                    explanation.is_some_and(|e| e.contains(hint)),
+                    "{api_url} {key}: {explanation:?}"
+                ),
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
+use color_eyre::{Result, eyre::eyre};
+use colored::Colorize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::config;
+use crate::diff;
+use crate::output::{self, say};
+use crate::prompt;
+
+/// Down to the microsecond, so two backups in the same second don't collide.
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.6fZ";
+
+pub struct Backup {
+    pub path: PathBuf,
+    pub taken_at: DateTime<Utc>,
+    pub version: String,
+}
+
+impl Backup {
+    /// Backups are named `<timestamp>_v<version>.cfg`.
+    fn from_path(path: PathBuf) -> Option<Self> {
+        let stem = path.file_stem()?.to_str()?;
+        let (timestamp, version) = stem.split_once("_v")?;
+        let taken_at = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+            .ok()?
+            .and_utc();
+        Some(Self {
+            version: version.to_string(),
+            taken_at,
+            path,
+        })
+    }
+
+    fn label(&self) -> String {
+        format!(
+            "{} (written by hackatime-setup v{})",
+            self.taken_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S"),
+            self.version
+        )
+    }
+}
+
+fn backups_dir() -> Result<PathBuf> {
+    Ok(config::wakatime_dir()?
+        .join("hackatime-setup")
+        .join("backups"))
+}
+
+/// Where backups of `config_path` go: one folder per config file, named
+/// after a hash of its absolute path and its file name, so `--config` never
+/// mixes them up with the default config's.
+pub fn backups_dir_for(config_path: &Path) -> Result<PathBuf> {
+    let absolute = std::path::absolute(config_path)?;
+    let hash = format!(
+        "{:x}",
+        Sha256::digest(absolute.as_os_str().as_encoded_bytes())
+    );
+    let file_name = absolute
+        .file_name()
+        .map_or_else(|| "config".into(), |n| n.to_string_lossy());
+    Ok(backups_dir()?.join(format!("{}-{}", &hash[..16], file_name)))
+}
+
+/// Lists backups of `config_path`, newest first.
+pub fn list_backups(config_path: &Path) -> Result<Vec<Backup>> {
+    let dir = backups_dir_for(config_path)?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups: Vec<Backup> = fs::read_dir(&dir)?
+        .flatten()
+        .filter_map(|entry| Backup::from_path(entry.path()))
+        .collect();
+    backups.sort_by_key(|b| std::cmp::Reverse(b.taken_at));
+    Ok(backups)
+}
+
+/// Copies the current config into its backups directory before it gets
+/// changed. Returns `None` when there was nothing new to back up.
+pub fn backup_config(config_path: &Path) -> Result<Option<PathBuf>> {
+    if !config_path.exists() {
+        return Ok(None);
+    }
+
+    let current = fs::read_to_string(config_path)?;
+    if let Some(latest) = list_backups(config_path)?.first()
+        && fs::read_to_string(&latest.path).is_ok_and(|s| s == current)
+    {
+        return Ok(None);
+    }
+
+    let dir = backups_dir_for(config_path)?;
+    fs::create_dir_all(&dir)?;
+    loop {
+        let backup_path = dir.join(format!(
+            "{}_v{}.cfg",
+            Utc::now().format(TIMESTAMP_FORMAT),
+            env!("CARGO_PKG_VERSION")
+        ));
+        // Never overwrite an earlier backup, however close together they are
+        let mut file = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&backup_path)
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(eyre!(
+                    "Failed to write backup {}: {}",
+                    backup_path.display(),
+                    e
+                ));
+            }
+        };
+        file.write_all(current.as_bytes())
+            .map_err(|e| eyre!("Failed to write backup {}: {}", backup_path.display(), e))?;
+        return Ok(Some(backup_path));
+    }
+}
+
+pub fn restore(config_path: &Path) -> Result<()> {
+    let backups = list_backups(config_path)?;
+
+    if backups.is_empty() {
+        say!(
+            "{} {}",
+            "No backups found of".dimmed(),
+            config::display_path(config_path).dimmed()
+        );
+        if output::is_json() {
+            output::emit("restore", json!({ "restored": false, "backup": null }));
+        }
+        return Ok(());
+    }
+
+    let labels: Vec<String> = backups.iter().map(Backup::label).collect();
+    let choice = prompt::select("Which backup should I restore?", &labels, 0, false)?;
+    let backup = &backups[choice];
+
+    let backup_contents = fs::read_to_string(&backup.path)?;
+    let current_contents = fs::read_to_string(config_path).unwrap_or_default();
+
+    if !output::is_json() {
+        println!(
+            "\nChanges to {} if I restore this backup:\n",
+            config::display_path(config_path).green()
+        );
+        diff::print_config_diff(&current_contents, &backup_contents)?;
+        println!();
+    }
+
+    let restore = prompt::confirm("Should I restore this backup?", true, false)?;
+
+    if !restore {
+        eprintln!("{}", "Understood, exiting now.".dimmed());
+        if output::is_json() {
+            output::emit(
+                "restore",
+                json!({ "restored": false, "backup": backup.path }),
+            );
+        }
+        return Ok(());
+    }
+
+    backup_config(config_path)?;
+    fs::write(config_path, backup_contents)?;
+    say!(
+        "{} {}",
+        "✔".green().bold(),
+        format!("Restored {}", config::display_path(config_path)).green()
+    );
+    if output::is_json() {
+        output::emit(
+            "restore",
+            json!({ "restored": true, "backup": backup.path }),
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Utc};
+use color_eyre::{Result, eyre::eyre};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zip::ZipArchive;
+use zip::write::SimpleFileOptions;
+
+use crate::download::{self, Artifact, Verified};
+use crate::output::{self, ErrorCode};
+
+/// Lists what's in the bundle, at the top of the zip.
+const MANIFEST: &str = "manifest.json";
+
+/// Where the downloads sit in the zip, under their artifact names.
+const FILES_DIR: &str = "files";
+
+/// The bundle `--from-bundle` setup installs from, if any.
+static ACTIVE: OnceLock<Bundle> = OnceLock::new();
+
+/// What a bundle holds, with the SHA-256 each file is checked against when
+/// it's installed.
+#[derive(Serialize, Deserialize)]
+pub struct Manifest {
+    pub created_at: DateTime<Utc>,
+    pub created_by: String,
+    pub files: Vec<BundledFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BundledFile {
+    pub name: String,
+    pub sha256: String,
+    pub bytes: u64,
+    /// Whether it matched a published SHA-256 when it was downloaded, rather
+    /// than being pinned by the bundle alone.
+    pub verified_upstream: bool,
+}
+
+/// An archive made by [`create`], opened for setup to install from.
+pub struct Bundle {
+    pub path: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl Bundle {
+    fn load(path: &Path) -> Result<Self> {
+        let mut zip = open_zip(path)?;
+        let manifest = zip
+            .by_name(MANIFEST)
+            .ok()
+            .and_then(|file| serde_json::from_reader(file).ok())
+            .ok_or_else(|| invalid(path, "it has no readable manifest"))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            manifest,
+        })
+    }
+
+    /// Reads `name` out of the bundle, checked against the manifest.
+    pub fn read(&self, name: &str) -> Result<Verified> {
+        let listed = self
+            .manifest
+            .files
+            .iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| {
+                invalid(
+                    &self.path,
+                    &format!(
+                        "it doesn't include {name}. Make a new one with `hackatime_setup bundle`"
+                    ),
+                )
+            })?;
+
+        let mut zip = open_zip(&self.path)?;
+        let mut bytes = Vec::new();
+        zip.by_name(&format!("{FILES_DIR}/{name}"))
+            .map_err(|_| invalid(&self.path, &format!("{name} is listed but missing")))?
+            .read_to_end(&mut bytes)
+            .map_err(|e| {
+                eyre!(
+                    "Failed to read {} from {}: {}",
+                    name,
+                    self.path.display(),
+                    e
+                )
+            })?;
+
+        let sha256 = format!("{:x}", Sha256::digest(&bytes));
+        if sha256 != listed.sha256 {
+            return Err(download::mismatch(name, &listed.sha256, &sha256));
+        }
+        Ok(Verified { bytes, sha256 })
+    }
+}
+
+/// Makes setup install from the bundle at `path` instead of downloading.
+pub fn open(path: &Path) -> Result<&'static Bundle> {
+    let bundle = Bundle::load(path)?;
+    Ok(ACTIVE.get_or_init(|| bundle))
+}
+
+/// The bundle given with `--from-bundle`, if any.
+pub fn active() -> Option<&'static Bundle> {
+    ACTIVE.get()
+}
+
+/// Downloads every artifact, checking each the way setup would, and writes
+/// them with a manifest to a zip at `output`.
+pub fn create(output: &Path, artifacts: &[Artifact]) -> Result<Manifest> {
+    let mut downloads = Vec::new();
+    for artifact in artifacts {
+        downloads.push((artifact, download::fetch(artifact)?));
+    }
+
+    let manifest = Manifest {
+        created_at: Utc::now(),
+        created_by: format!("hackatime-setup {}", env!("CARGO_PKG_VERSION")),
+        files: downloads
+            .iter()
+            .map(|(artifact, download)| BundledFile {
+                name: artifact.name(),
+                sha256: download.sha256.clone(),
+                bytes: download.bytes.len() as u64,
+                verified_upstream: artifact.is_checked(),
+            })
+            .collect(),
+    };
+
+    // Write next to the output and rename, so a stick never holds half a bundle
+    let partial = output.with_extension("download");
+    let file = File::create(&partial)
+        .map_err(|e| eyre!("Failed to create {}: {}", partial.display(), e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    // The downloads are zips already, so compressing them again gains nothing
+    let options = SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Stored)
+        .large_file(true);
+
+    zip.start_file(MANIFEST, options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+    for (artifact, download) in &downloads {
+        zip.start_file(format!("{FILES_DIR}/{}", artifact.name()), options)?;
+        zip.write_all(&download.bytes)?;
+    }
+    zip.finish()
+        .map_err(|e| eyre!("Failed to write {}: {}", partial.display(), e))?;
+
+    fs::rename(&partial, output)
+        .map_err(|e| eyre!("Failed to save {}: {}", output.display(), e))?;
+    Ok(manifest)
+}
+
+fn open_zip(path: &Path) -> Result<ZipArchive<File>> {
+    let file = File::open(path).map_err(|e| {
+        output::coded(
+            ErrorCode::BundleInvalid,
+            format!("Couldn't open the bundle {}: {}", path.display(), e),
+        )
+    })?;
+    ZipArchive::new(file).map_err(|_| invalid(path, "it isn't a zip"))
+}
+
+fn invalid(path: &Path, reason: &str) -> color_eyre::Report {
+    output::coded(
+        ErrorCode::BundleInvalid,
+        format!(
+            "{} isn't a hackatime-setup bundle I can use: {}.",
+            path.display(),
+            reason
+        ),
+    )
+}
@@ -0,0 +1,156 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use color_eyre::{Result, eyre::eyre};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Downloads kept between runs, so re-running setup on a slow network only
+/// re-downloads what changed.
+#[cfg(not(test))]
+pub fn cache_dir() -> Result<PathBuf> {
+    Ok(crate::config::wakatime_dir()?
+        .join("hackatime-setup")
+        .join("cache"))
+}
+
+/// Tests share one temporary cache instead of the real one. Each test server
+/// has its own port, so their entries never collide.
+#[cfg(test)]
+pub fn cache_dir() -> Result<PathBuf> {
+    static DIR: std::sync::LazyLock<tempfile::TempDir> =
+        std::sync::LazyLock::new(|| tempfile::tempdir().unwrap());
+    Ok(DIR.path().to_path_buf())
+}
+
+/// How to revalidate a cached download, stored next to it as `<file>.json`.
+#[derive(Serialize, Deserialize)]
+pub struct Meta {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// One URL's place in the cache: the finished file, or a `.part` file while
+/// it's still downloading.
+pub struct Entry {
+    pub path: PathBuf,
+}
+
+impl Entry {
+    /// Named after the URL's hash and file name, e.g. `3f2a...-macos-wakatime.zip`.
+    pub fn for_url(url: &str) -> Result<Self> {
+        let hash = format!("{:x}", Sha256::digest(url.as_bytes()));
+        let file_name = url
+            .split(['?', '#'])
+            .next()
+            .unwrap_or(url)
+            .rsplit('/')
+            .next()
+            .filter(|name| !name.is_empty())
+            .unwrap_or("download");
+        Ok(Self {
+            path: cache_dir()?.join(format!("{}-{}", &hash[..16], file_name)),
+        })
+    }
+
+    pub fn part_path(&self) -> PathBuf {
+        self.path.with_extension(extension(&self.path, "part"))
+    }
+
+    fn meta_path(&self) -> PathBuf {
+        self.path.with_extension(extension(&self.path, "json"))
+    }
+
+    pub fn meta(&self) -> Option<Meta> {
+        serde_json::from_slice(&fs::read(self.meta_path()).ok()?).ok()
+    }
+
+    pub fn write_meta(&self, meta: &Meta) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| eyre!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        fs::write(self.meta_path(), serde_json::to_vec_pretty(meta)?)
+            .map_err(|e| eyre!("Failed to write {}: {}", self.meta_path().display(), e))
+    }
+
+    /// Forgets the download, e.g. after it failed its checksum.
+    pub fn remove(&self) {
+        for path in [&self.path, &self.part_path(), &self.meta_path()] {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Appends `suffix` to the whole file name, so `a.zip` becomes `a.zip.part`.
+fn extension(path: &Path, suffix: &str) -> String {
+    match path.extension() {
+        Some(ext) => format!("{}.{}", ext.to_string_lossy(), suffix),
+        None => suffix.to_string(),
+    }
+}
+
+/// A download in the cache, for `cache list`.
+#[derive(Serialize)]
+pub struct Cached {
+    pub url: String,
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// Still a `.part` file: the next download picks up where it stopped.
+    pub partial: bool,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// Lists cached downloads, newest first.
+pub fn list() -> Result<Vec<Cached>> {
+    let dir = cache_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut cached = Vec::new();
+    for entry in fs::read_dir(&dir)?.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|e| e == "json") {
+            continue;
+        }
+        let partial = path.extension().is_some_and(|e| e == "part");
+        let entry = Entry {
+            path: if partial {
+                path.with_extension("")
+            } else {
+                path.clone()
+            },
+        };
+        let Some(meta) = entry.meta() else {
+            continue;
+        };
+        cached.push(Cached {
+            url: meta.url,
+            bytes: fs::metadata(&path).map(|m| m.len()).unwrap_or_default(),
+            path,
+            partial,
+            fetched_at: meta.fetched_at,
+        });
+    }
+    cached.sort_by_key(|c| std::cmp::Reverse(c.fetched_at));
+    Ok(cached)
+}
+
+/// Deletes every cached download, returning how many bytes that freed.
+pub fn clear() -> Result<u64> {
+    let dir = cache_dir()?;
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let freed = fs::read_dir(&dir)?
+        .flatten()
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum();
+    fs::remove_dir_all(&dir).map_err(|e| eyre!("Failed to clear {}: {}", dir.display(), e))?;
+    Ok(freed)
+}
@@ -0,0 +1,74 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use color_eyre::{Result, eyre::eyre};
+use reqwest::header::DATE;
+use serde::Serialize;
+
+use crate::http;
+
+/// Heartbeats stamped further than this from the server's clock may be
+/// dropped or land at the wrong time on the dashboard.
+const MAX_SKEW: TimeDelta = TimeDelta::seconds(60);
+
+/// How far the local clock is from the server's.
+#[derive(Serialize)]
+pub struct ClockSkew {
+    /// Positive when the local clock is ahead.
+    pub offset_seconds: i64,
+    pub server_time: DateTime<Utc>,
+}
+
+impl ClockSkew {
+    pub fn is_significant(&self) -> bool {
+        self.offset_seconds.abs() > MAX_SKEW.num_seconds()
+    }
+
+    /// E.g. "5m 12s ahead of".
+    pub fn describe(&self) -> String {
+        let seconds = self.offset_seconds.unsigned_abs();
+        let amount = match seconds {
+            0..60 => format!("{seconds}s"),
+            60..3600 => format!("{}m {}s", seconds / 60, seconds % 60),
+            _ => format!("{}h {}m", seconds / 3600, seconds % 3600 / 60),
+        };
+        let direction = if self.offset_seconds >= 0 {
+            "ahead of"
+        } else {
+            "behind"
+        };
+        format!("{amount} {direction}")
+    }
+}
+
+/// Compares the local clock with the `Date` header of a request to the
+/// server. The header only has whole seconds, so small offsets are noise.
+pub fn measure(api_url: &str) -> Result<ClockSkew> {
+    let before = Utc::now();
+    let response = http::send(http::client()?.get(format!("{api_url}/users/current")))?;
+    let after = Utc::now();
+
+    let date = response
+        .headers()
+        .get(DATE)
+        .and_then(|d| d.to_str().ok())
+        .ok_or_else(|| eyre!("The server didn't send a Date header"))?;
+    let server_time = DateTime::parse_from_rfc2822(date)
+        .map_err(|e| eyre!("The server sent an invalid Date header ({}): {}", date, e))?
+        .with_timezone(&Utc);
+
+    let local_time = before + (after - before) / 2;
+    Ok(ClockSkew {
+        offset_seconds: (local_time - server_time).num_seconds(),
+        server_time,
+    })
+}
+
+/// How to turn on automatic time sync on this OS.
+pub fn ntp_hint() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "Turn on \"Set time and date automatically\" in System Settings > General > Date & Time, or run: sudo sntp -sS time.apple.com"
+    } else if cfg!(windows) {
+        "Turn on \"Set time automatically\" in Settings > Time & language > Date & time and click \"Sync now\", or run: w32tm /resync"
+    } else {
+        "Turn on time sync with: sudo timedatectl set-ntp true"
+    }
+}
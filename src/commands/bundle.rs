@@ -0,0 +1,62 @@
+use std::path::Path;
+
+use color_eyre::Result;
+use colored::Colorize;
+use indicatif::HumanBytes;
+
+use crate::output::{self, CodedError, ErrorCode};
+use crate::{bundle, config, editor_plugins, wakatime_cli};
+
+pub fn create(source: &wakatime_cli::Source, path: &Path) -> Result<()> {
+    let mut artifacts = wakatime_cli::bundle_artifacts(source);
+    artifacts.extend(editor_plugins::bundle_artifacts());
+
+    let manifest =
+        bundle::create(path, &artifacts).map_err(|e| match e.downcast_ref::<CodedError>() {
+            Some(_) => e,
+            None => output::coded(ErrorCode::BundleCreateFailed, e.to_string()),
+        })?;
+    let total: u64 = manifest.files.iter().map(|f| f.bytes).sum();
+
+    if output::is_json() {
+        output::emit(
+            "bundle",
+            serde_json::json!({
+                "path": path,
+                "total_bytes": total,
+                "manifest": manifest,
+            }),
+        );
+        return Ok(());
+    }
+
+    for file in &manifest.files {
+        println!(
+            "{} {:<36} {:>10}{}",
+            "✔".green().bold(),
+            file.name,
+            HumanBytes(file.bytes).to_string(),
+            if file.verified_upstream {
+                String::new()
+            } else {
+                "  (no published checksum, pinned by the bundle)"
+                    .dimmed()
+                    .to_string()
+            }
+        );
+    }
+    println!(
+        "\n{} in {}",
+        HumanBytes(total).to_string().bold(),
+        config::display_path(path)
+    );
+    println!(
+        "{}",
+        format!(
+            "Copy it to each computer and run: hackatime_setup --from-bundle {}",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        )
+        .dimmed()
+    );
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use chrono::Local;
+use color_eyre::Result;
+use colored::Colorize;
+use indicatif::HumanBytes;
+
+use crate::{cache, config, output};
+
+pub fn list() -> Result<()> {
+    let dir = cache::cache_dir()?;
+    let downloads = cache::list()?;
+    let total: u64 = downloads.iter().map(|d| d.bytes).sum();
+
+    if output::is_json() {
+        output::emit(
+            "cache list",
+            serde_json::json!({
+                "path": dir,
+                "total_bytes": total,
+                "downloads": downloads,
+            }),
+        );
+        return Ok(());
+    }
+
+    if downloads.is_empty() {
+        println!(
+            "{} {}",
+            "Nothing cached in".dimmed(),
+            config::display_path(&dir).dimmed()
+        );
+        return Ok(());
+    }
+
+    for download in &downloads {
+        println!(
+            "{:>10}  {}  {}{}",
+            HumanBytes(download.bytes).to_string(),
+            download
+                .fetched_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+                .dimmed(),
+            download.url,
+            if download.partial {
+                " (partial)".yellow().to_string()
+            } else {
+                String::new()
+            }
+        );
+    }
+    println!(
+        "\n{} in {}",
+        HumanBytes(total).to_string().bold(),
+        config::display_path(&dir)
+    );
+    Ok(())
+}
+
+pub fn clear() -> Result<()> {
+    let dir = cache::cache_dir()?;
+    let freed = cache::clear()?;
+
+    if output::is_json() {
+        output::emit(
+            "cache clear",
+            serde_json::json!({ "path": dir, "freed_bytes": freed }),
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{} Freed {} from {}",
+        "✔".green().bold(),
+        HumanBytes(freed),
+        config::display_path(&dir)
+    );
+    Ok(())
+}
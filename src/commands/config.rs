@@ -0,0 +1,124 @@
+use std::fs;
+use std::path::Path;
+
+use color_eyre::Result;
+use colored::Colorize;
+use ini::Ini;
+use serde_json::json;
+
+use crate::output::{self, ErrorCode, say};
+use crate::{api_key, backup, config, server};
+
+fn read_config(config_path: &Path) -> Result<String> {
+    fs::read_to_string(config_path).map_err(|e| {
+        output::coded(
+            ErrorCode::ConfigNotFound,
+            format!(
+                "Couldn't read {}: {}. Run hackatime_setup to create it.",
+                config::display_path(config_path),
+                e
+            ),
+        )
+    })
+}
+
+pub fn show(config_path: &Path, show_key: bool) -> Result<()> {
+    let contents = read_config(config_path)?;
+
+    let contents = if show_key {
+        contents
+    } else {
+        config::mask_api_key(&contents)?
+    };
+
+    if output::is_json() {
+        let sections = config::sections_json(&contents)?;
+        output::emit(
+            "config show",
+            json!({ "path": config_path, "sections": sections }),
+        );
+        return Ok(());
+    }
+
+    println!("{}\n", config::display_path(config_path).green());
+    config::print_ini(&contents)
+}
+
+pub fn get(config_path: &Path, section: &str, key: &str) -> Result<()> {
+    let conf = config::parse_config(&read_config(config_path)?)?;
+
+    match conf.get_from(Some(section), key) {
+        Some(value) => {
+            if output::is_json() {
+                output::emit(
+                    "config get",
+                    json!({ "section": section, "key": key, "value": value }),
+                );
+            } else {
+                println!("{value}");
+            }
+            Ok(())
+        }
+        None => Err(output::coded(
+            ErrorCode::KeyNotSet,
+            format!("{section}.{key} is not set"),
+        )),
+    }
+}
+
+pub fn set(config_path: &Path, section: &str, key: &str, value: &str) -> Result<()> {
+    let existing = if config_path.exists() {
+        read_config(config_path)?
+    } else {
+        String::new()
+    };
+
+    if section == "settings" && key == "api_key" {
+        let conf = config::parse_config(&existing)?;
+        let api_url = conf
+            .get_from(Some("settings"), "api_url")
+            .unwrap_or(server::WAKATIME_API_URL);
+        api_key::validate_api_key(api_url, value)
+            .map_err(|e| output::coded(ErrorCode::InvalidApiKey, e))?;
+    }
+
+    let mut update = Ini::new();
+    update.with_section(Some(section)).set(key, value);
+    let updated = config::merge_config(&existing, &update, &[]);
+
+    let backup_path = backup::backup_config(config_path)?;
+    if let Some(backup_path) = &backup_path {
+        say!(
+            "{} {}",
+            "Backed up your previous config to".dimmed(),
+            config::display_path(backup_path).dimmed()
+        );
+    }
+
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(config_path, updated)?;
+    say!(
+        "{} {}",
+        "✔".green().bold(),
+        format!(
+            "Set {section}.{key} in {}",
+            config::display_path(config_path)
+        )
+        .green()
+    );
+    if output::is_json() {
+        output::emit(
+            "config set",
+            json!({
+                "path": config_path,
+                "section": section,
+                "key": key,
+                "backup": backup_path,
+            }),
+        );
+    }
+
+    Ok(())
+}
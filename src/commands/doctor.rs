@@ -0,0 +1,283 @@
+use std::fs;
+use std::path::Path;
+
+use color_eyre::Result;
+use colored::Colorize;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::{api, api_key, clock, config, editor_plugins, http, output, server, wakatime_cli};
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+#[derive(Serialize)]
+struct Check {
+    status: Status,
+    title: String,
+    hint: Option<String>,
+}
+
+impl Check {
+    fn ok(title: impl Into<String>) -> Self {
+        Self {
+            status: Status::Ok,
+            title: title.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(title: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            status: Status::Warn,
+            title: title.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn fail(title: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            status: Status::Fail,
+            title: title.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn print(&self) {
+        let icon = match self.status {
+            Status::Ok => "✔".green(),
+            Status::Warn => "!".yellow(),
+            Status::Fail => "✘".red(),
+        };
+        println!("{} {}", icon.bold(), self.title);
+        if let Some(hint) = &self.hint {
+            println!("  {}", hint.dimmed());
+        }
+    }
+}
+
+pub fn run(config_path: &Path, network: &http::NetworkSettings) -> Result<()> {
+    let mut checks = Vec::new();
+    let settings = check_config(config_path, &mut checks);
+    checks.extend(check_wakatime_cli());
+    checks.extend(check_editors());
+    let network_ok = check_network(network, &mut checks);
+    if network_ok && let Some((api_url, key)) = settings {
+        checks.push(check_api(&api_url, &key));
+        checks.extend(check_clock(&api_url));
+    }
+
+    let failures = checks
+        .iter()
+        .filter(|c| matches!(c.status, Status::Fail))
+        .count();
+    let warnings = checks
+        .iter()
+        .filter(|c| matches!(c.status, Status::Warn))
+        .count();
+
+    if output::is_json() {
+        output::emit(
+            "doctor",
+            serde_json::json!({
+                "ok": failures == 0,
+                "failures": failures,
+                "warnings": warnings,
+                "checks": checks,
+            }),
+        );
+        if failures > 0 {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    for check in &checks {
+        check.print();
+    }
+
+    println!();
+    if failures > 0 {
+        println!(
+            "{}",
+            format!("{failures} problem(s) and {warnings} warning(s) found.")
+                .red()
+                .bold()
+        );
+        std::process::exit(1);
+    } else if warnings > 0 {
+        println!(
+            "{}",
+            format!("{warnings} warning(s) found.").yellow().bold()
+        );
+    } else {
+        println!("{}", "Everything looks good!".green().bold());
+    }
+
+    Ok(())
+}
+
+/// Checks the config file and returns the API URL and key when they're usable.
+fn check_config(config_path: &Path, checks: &mut Vec<Check>) -> Option<(String, String)> {
+    let display = config::display_path(config_path);
+
+    let Ok(contents) = fs::read_to_string(config_path) else {
+        checks.push(Check::fail(
+            format!("No config at {display}"),
+            "Run hackatime_setup to create one.",
+        ));
+        return None;
+    };
+
+    let conf = match config::parse_config(&contents) {
+        Ok(conf) => conf,
+        Err(e) => {
+            checks.push(Check::fail(
+                format!("{display} can't be parsed"),
+                format!("{e}. Fix it by hand or run hackatime_setup restore."),
+            ));
+            return None;
+        }
+    };
+    checks.push(Check::ok(format!("Config found at {display}")));
+
+    let api_url = conf.get_from(Some("settings"), "api_url");
+    let key = conf.get_from(Some("settings"), "api_key");
+
+    match api_url {
+        Some(url) => checks.push(Check::ok(format!("API URL is {url}"))),
+        None => checks.push(Check::fail(
+            "No api_url in [settings]",
+            "Without it, heartbeats go to wakatime.com instead of Hackatime. Run hackatime_setup.",
+        )),
+    }
+
+    // wakatime-cli sends heartbeats to WakaTime when api_url is missing
+    let key_server = api_url.unwrap_or(server::WAKATIME_API_URL);
+    match key.map(|k| (k, api_key::validate_api_key(key_server, k))) {
+        Some((_, Ok(()))) => checks.push(Check::ok("API key looks valid")),
+        Some((_, Err(e))) => checks.push(Check::fail("API key is malformed", e)),
+        None => checks.push(Check::fail(
+            "No api_key in [settings]",
+            "Run hackatime_setup with the key from your Hackatime settings page.",
+        )),
+    }
+
+    Some((api_url?.to_string(), key?.to_string()))
+}
+
+/// Sets up the network settings for the checks that make requests, and says
+/// whether they're usable.
+fn check_network(network: &http::NetworkSettings, checks: &mut Vec<Check>) -> bool {
+    if let Err(e) = http::configure(network) {
+        checks.push(Check::fail(
+            "Your network settings can't be used",
+            format!(
+                "{e}. Fix them with hackatime_setup config set, or run hackatime_setup restore."
+            ),
+        ));
+        return false;
+    }
+    if let Some(proxy) = &network.proxy {
+        checks.push(Check::ok(format!("Requests go through the proxy {proxy}")));
+    }
+    true
+}
+
+fn check_wakatime_cli() -> Option<Check> {
+    let path = wakatime_cli::binary_path().ok()?;
+    if !path.exists() {
+        return Some(Check::warn(
+            format!(
+                "wakatime-cli isn't in {} yet",
+                config::display_path(path.parent()?)
+            ),
+            "Run hackatime_setup wakatime-cli install, or open your editor and let the plugin download it.",
+        ));
+    }
+
+    Some(match wakatime_cli::version(&path) {
+        Some(version) => Check::ok(format!(
+            "wakatime-cli {} is at {}",
+            version,
+            config::display_path(&path)
+        )),
+        None => Check::fail(
+            format!("{} doesn't run", config::display_path(&path)),
+            "Reinstall it with hackatime_setup wakatime-cli install --force",
+        ),
+    })
+}
+
+fn check_editors() -> Vec<Check> {
+    let editors: Vec<_> = editor_plugins::all_editors()
+        .into_par_iter()
+        .filter(|e| e.is_installed())
+        .map(|e| (e.name(), e.id(), e.is_plugin_installed()))
+        .collect();
+
+    if editors.is_empty() {
+        return vec![Check::warn(
+            "No supported editors found",
+            "See https://hackatime.hackclub.com/docs for other editors.",
+        )];
+    }
+
+    editors
+        .into_iter()
+        .map(|(name, id, has_plugin)| {
+            if has_plugin {
+                Check::ok(format!("{name} has the WakaTime plugin"))
+            } else {
+                Check::warn(
+                    format!("{name} doesn't have the WakaTime plugin"),
+                    format!("Run hackatime_setup editors install {id}"),
+                )
+            }
+        })
+        .collect()
+}
+
+fn check_clock(api_url: &str) -> Option<Check> {
+    let skew = clock::measure(api_url).ok()?;
+    Some(if skew.is_significant() {
+        Check::warn(
+            format!("Your clock is {} the server's", skew.describe()),
+            format!(
+                "Heartbeats may be dropped or land at the wrong time. {}",
+                clock::ntp_hint()
+            ),
+        )
+    } else {
+        Check::ok("Your clock matches the server's")
+    })
+}
+
+fn check_api(api_url: &str, key: &str) -> Check {
+    match api::current_user(api_url, key) {
+        Ok(account) => Check::ok(format!(
+            "{api_url} accepts your API key ({})",
+            account.label()
+        )),
+        Err(api::ApiError::Unauthorized(_)) => Check::fail(
+            "The server rejected your API key",
+            "Copy the key again from your Hackatime settings page and run hackatime_setup.",
+        ),
+        Err(api::ApiError::Status(status)) => Check::warn(
+            format!("The server answered with HTTP {status}"),
+            "The API URL may be wrong, or the server may be having trouble.",
+        ),
+        Err(e @ api::ApiError::Unreadable(_)) => Check::fail(
+            format!("{api_url} doesn't look like a WakaTime-compatible API"),
+            format!("{e}. Check api_url in your config."),
+        ),
+        Err(e @ api::ApiError::Network(_)) => {
+            Check::fail(format!("Couldn't reach {api_url}"), e.to_string())
+        }
+    }
+}
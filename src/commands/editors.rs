@@ -0,0 +1,99 @@
+use color_eyre::Result;
+use colored::Colorize;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::commands::setup::{self, EditorReport};
+use crate::editor_plugins::{self, EditorPlugin};
+use crate::output;
+
+#[derive(Serialize)]
+struct EditorInfo {
+    id: String,
+    name: String,
+    installed: bool,
+    plugin_installed: bool,
+}
+
+pub fn list() -> Result<()> {
+    let editors: Vec<(Box<dyn EditorPlugin>, bool, bool)> = editor_plugins::all_editors()
+        .into_par_iter()
+        .map(|e| {
+            let installed = e.is_installed();
+            let has_plugin = installed && e.is_plugin_installed();
+            (e, installed, has_plugin)
+        })
+        .collect();
+
+    if output::is_json() {
+        let editors: Vec<EditorInfo> = editors
+            .iter()
+            .map(|(e, installed, has_plugin)| EditorInfo {
+                id: e.id(),
+                name: e.name(),
+                installed: *installed,
+                plugin_installed: *has_plugin,
+            })
+            .collect();
+        output::emit("editors list", serde_json::json!({ "editors": editors }));
+        return Ok(());
+    }
+
+    let id_width = editors
+        .iter()
+        .map(|(e, ..)| e.id().len())
+        .max()
+        .unwrap_or(0);
+    let name_width = editors
+        .iter()
+        .map(|(e, ..)| e.name().len())
+        .max()
+        .unwrap_or(0);
+
+    for (editor, installed, has_plugin) in &editors {
+        let state = match (installed, has_plugin) {
+            (true, true) => "plugin installed".green(),
+            (true, false) => "detected, no plugin".yellow(),
+            (false, _) => "not found".dimmed(),
+        };
+        println!(
+            "{:id_width$}  {:name_width$}  {}",
+            editor.id().cyan(),
+            editor.name(),
+            state
+        );
+    }
+
+    Ok(())
+}
+
+pub fn install(requested: &[String]) -> Result<()> {
+    setup::validate_editor_ids(requested)?;
+
+    let (installed_editors, missing_editors): (Vec<_>, Vec<_>) = editor_plugins::all_editors()
+        .into_par_iter()
+        .partition(|e| e.is_installed());
+
+    let selected_editors = setup::select_editors_by_id(&installed_editors, requested);
+    let outcomes = if selected_editors.is_empty() {
+        Vec::new()
+    } else {
+        setup::install_plugins(&selected_editors)
+    };
+
+    if output::is_json() {
+        let mut reports: Vec<EditorReport> = installed_editors
+            .iter()
+            .map(|e| EditorReport::new(e.as_ref(), true))
+            .chain(
+                missing_editors
+                    .iter()
+                    .map(|e| EditorReport::new(e.as_ref(), false)),
+            )
+            .collect();
+        setup::record_outcomes(&mut reports, &selected_editors, outcomes);
+        output::emit("editors install", serde_json::json!({ "editors": reports }));
+    }
+
+    Ok(())
+}
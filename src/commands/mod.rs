@@ -0,0 +1,9 @@
+pub mod bundle;
+pub mod cache;
+pub mod config;
+pub mod doctor;
+pub mod editors;
+pub mod setup;
+pub mod status;
+pub mod uninstall;
+pub mod wakatime_cli;
@@ -0,0 +1,246 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use color_eyre::Result;
+use colored::Colorize;
+use rayon::iter::Either;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::{api, api_key, backup, config, editor_plugins, output, wakatime_cli};
+
+/// Everything `status` reports, printed as text or emitted as JSON.
+#[derive(Serialize)]
+struct StatusReport {
+    config: ConfigStatus,
+    account: Option<api::Account>,
+    /// Why the account couldn't be looked up, e.g. a rejected key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    account_error: Option<String>,
+    today: Option<api::TodaySummary>,
+    wakatime_cli: Option<CliStatus>,
+    /// Detected editors with the plugin, by id.
+    tracking: Vec<Editor>,
+    /// Detected editors without the plugin, by id.
+    no_plugin: Vec<Editor>,
+    backups: BackupsStatus,
+}
+
+#[derive(Serialize)]
+struct ConfigStatus {
+    path: PathBuf,
+    exists: bool,
+    api_url: Option<String>,
+    /// Masked
+    api_key: Option<String>,
+    hostname: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CliStatus {
+    version: Option<String>,
+    path: PathBuf,
+}
+
+/// An editor, serialized as its id.
+#[derive(Clone, Serialize)]
+#[serde(into = "String")]
+struct Editor {
+    id: String,
+    name: String,
+}
+
+impl From<Editor> for String {
+    fn from(editor: Editor) -> Self {
+        editor.id
+    }
+}
+
+#[derive(Serialize)]
+struct BackupsStatus {
+    count: usize,
+    latest: Option<DateTime<Utc>>,
+}
+
+pub fn run(config_path: &Path) -> Result<()> {
+    let report = collect(config_path)?;
+    if output::is_json() {
+        output::emit("status", &report);
+    } else {
+        print(&report);
+    }
+    Ok(())
+}
+
+fn collect(config_path: &Path) -> Result<StatusReport> {
+    let conf = match fs::read_to_string(config_path) {
+        Ok(contents) => Some(config::parse_config(&contents)?),
+        Err(_) => None,
+    };
+    let get = |key: &str| {
+        conf.as_ref()
+            .and_then(|conf| conf.get_from(Some("settings"), key))
+            .map(str::to_string)
+    };
+
+    let (mut account, mut account_error, mut today) = (None, None, None);
+    if let (Some(api_url), Some(key)) = (get("api_url"), get("api_key")) {
+        match api::current_user(&api_url, &key) {
+            Ok(found) => {
+                account = Some(found);
+                today = api::today(&api_url, &key).ok().flatten();
+            }
+            Err(e) => account_error = Some(e.to_string()),
+        }
+    }
+
+    let wakatime_cli = wakatime_cli::binary_path()
+        .ok()
+        .filter(|path| path.exists())
+        .map(|path| CliStatus {
+            version: wakatime_cli::version(&path),
+            path,
+        });
+
+    let (tracking, no_plugin): (Vec<_>, Vec<_>) = editor_plugins::all_editors()
+        .into_par_iter()
+        .filter(|e| e.is_installed())
+        .partition_map(|e| {
+            let editor = Editor {
+                id: e.id(),
+                name: e.name(),
+            };
+            if e.is_plugin_installed() {
+                Either::Left(editor)
+            } else {
+                Either::Right(editor)
+            }
+        });
+
+    let backups = backup::list_backups(config_path)?;
+
+    Ok(StatusReport {
+        config: ConfigStatus {
+            path: config_path.to_path_buf(),
+            exists: conf.is_some(),
+            api_url: get("api_url"),
+            api_key: get("api_key").map(|key| api_key::mask_api_key(&key)),
+            hostname: get("hostname"),
+        },
+        account,
+        account_error,
+        today,
+        wakatime_cli,
+        tracking,
+        no_plugin,
+        backups: BackupsStatus {
+            count: backups.len(),
+            latest: backups.first().map(|b| b.taken_at),
+        },
+    })
+}
+
+fn print(report: &StatusReport) {
+    let label = |s: &str| format!("{s:>10}").dimmed();
+    let names = |editors: &[Editor]| {
+        editors
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let config = &report.config;
+    println!("{} {}", label("Config"), config::display_path(&config.path));
+    if config.exists {
+        println!(
+            "{} {}",
+            label("API URL"),
+            config.api_url.as_deref().unwrap_or("(not set)")
+        );
+        println!(
+            "{} {}",
+            label("API key"),
+            config.api_key.as_deref().unwrap_or("(not set)")
+        );
+        println!(
+            "{} {}",
+            label("Machine"),
+            config
+                .hostname
+                .as_deref()
+                .unwrap_or("(your computer's hostname)")
+        );
+    } else {
+        println!(
+            "{} {}",
+            label(""),
+            "not found, run hackatime_setup".yellow()
+        );
+    }
+
+    if let Some(account) = &report.account {
+        println!("{} {}", label("Account"), account.label());
+    }
+    if let Some(error) = &report.account_error {
+        println!("{} {}", label("Account"), error.yellow());
+    }
+    if let Some(today) = &report.today {
+        println!("{} {}", label("Today"), describe_today(today));
+    }
+
+    println!(
+        "{} {}",
+        label("CLI"),
+        match &report.wakatime_cli {
+            Some(cli) => format!(
+                "wakatime-cli {}",
+                cli.version.as_deref().unwrap_or("installed")
+            ),
+            None => "not installed yet".yellow().to_string(),
+        }
+    );
+
+    println!(
+        "{} {}",
+        label("Tracking"),
+        if report.tracking.is_empty() {
+            "no editors".yellow().to_string()
+        } else {
+            names(&report.tracking).green().to_string()
+        }
+    );
+    if !report.no_plugin.is_empty() {
+        println!(
+            "{} {}",
+            label("No plugin"),
+            names(&report.no_plugin).yellow()
+        );
+    }
+
+    println!(
+        "{} {}",
+        label("Backups"),
+        match report.backups.latest {
+            Some(latest) => format!(
+                "{} (latest {})",
+                report.backups.count,
+                latest.format("%Y-%m-%d")
+            ),
+            None => "none".to_string(),
+        }
+    );
+}
+
+fn describe_today(today: &api::TodaySummary) -> String {
+    if today.grand_total.total_seconds == 0.0 {
+        return "nothing tracked yet".to_string();
+    }
+    let machines: Vec<&str> = today.machines.iter().map(|m| m.name.as_str()).collect();
+    if machines.is_empty() {
+        today.grand_total.text.clone()
+    } else {
+        format!("{} on {}", today.grand_total.text, machines.join(", "))
+    }
+}
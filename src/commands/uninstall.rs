@@ -0,0 +1,130 @@
+use std::fs;
+use std::path::Path;
+
+use color_eyre::Result;
+use colored::Colorize;
+use indicatif::ProgressBar;
+use rayon::prelude::*;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::editor_plugins;
+use crate::output::{self, ErrorCode, ErrorInfo, Outcome, say};
+use crate::{UninstallArgs, backup, config, prompt};
+
+#[derive(Serialize)]
+struct UninstallReport {
+    id: String,
+    name: String,
+    result: Outcome,
+}
+
+pub fn run(args: &UninstallArgs, config_path: &Path) -> Result<()> {
+    if !args.yes && !prompt::can_prompt() {
+        return Err(output::coded(
+            ErrorCode::NoTerminal,
+            "No terminal available to confirm the uninstall. Re-run with --yes to uninstall without asking.",
+        ));
+    }
+
+    let mut reports = Vec::new();
+
+    let editors: Vec<_> = editor_plugins::all_editors()
+        .into_par_iter()
+        .filter(|e| e.is_installed() && e.is_plugin_installed())
+        .collect();
+
+    if editors.is_empty() {
+        say!(
+            "{}",
+            "No editors have the WakaTime plugin installed.".dimmed()
+        );
+    } else {
+        let editor_names: Vec<String> = editors.iter().map(|e| e.name()).collect();
+        let selections = prompt::multi_select(
+            "Which editors should I remove the WakaTime plugin from? (space to select/unselect)",
+            &editor_names,
+            args.yes,
+        )?;
+
+        for editor in selections.into_iter().map(|i| &editors[i]) {
+            let name = editor.name();
+            let pb = output::progress_bar(ProgressBar::new_spinner());
+            pb.set_message(format!("Uninstalling from {name}..."));
+            pb.enable_steady_tick(std::time::Duration::from_millis(80));
+
+            let result = match editor.uninstall() {
+                Ok(()) => {
+                    pb.finish_with_message(format!("{} Uninstalled from {}", "✔".green(), name));
+                    Outcome::Ok
+                }
+                Err(e) => {
+                    pb.finish_with_message(format!("{} {} failed: {}", "✘".red(), name, e));
+                    Outcome::Failed {
+                        error: ErrorInfo::new(ErrorCode::PluginUninstallFailed, e),
+                    }
+                }
+            };
+            reports.push(UninstallReport {
+                id: editor.id(),
+                name,
+                result,
+            });
+        }
+    }
+
+    let emit = |config_changed: bool, backup_path: Option<&Path>| {
+        if output::is_json() {
+            output::emit(
+                "uninstall",
+                json!({
+                    "editors": reports,
+                    "config": { "changed": config_changed, "backup": backup_path },
+                }),
+            );
+        }
+    };
+
+    if args.keep_config || !config_path.exists() {
+        emit(false, None);
+        return Ok(());
+    }
+
+    let remove = prompt::confirm(
+        &format!(
+            "Remove your Hackatime API key and URL from {}?",
+            config::display_path(config_path)
+        ),
+        true,
+        args.yes,
+    )?;
+    if !remove {
+        emit(false, None);
+        return Ok(());
+    }
+
+    let existing = fs::read_to_string(config_path)?;
+    let updated = config::remove_keys(&existing, Some("settings"), &["api_key", "api_url"]);
+
+    let backup_path = backup::backup_config(config_path)?;
+    if let Some(backup_path) = &backup_path {
+        say!(
+            "{} {}",
+            "Backed up your previous config to".dimmed(),
+            config::display_path(backup_path).dimmed()
+        );
+    }
+    fs::write(config_path, updated)?;
+    say!(
+        "{} {}",
+        "✔".green().bold(),
+        format!(
+            "Removed Hackatime from {}",
+            config::display_path(config_path)
+        )
+        .green()
+    );
+    emit(true, backup_path.as_deref());
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use color_eyre::Result;
+use colored::Colorize;
+use indicatif::ProgressBar;
+
+use crate::output::{self, CodedError, ErrorCode};
+use crate::{config, wakatime_cli};
+
+pub fn install(source: &wakatime_cli::Source, force: bool) -> Result<()> {
+    let pb = output::progress_bar(ProgressBar::new_spinner());
+    pb.set_message("Installing wakatime-cli...");
+    pb.enable_steady_tick(std::time::Duration::from_millis(80));
+    let result = wakatime_cli::install(source, force);
+    pb.finish_and_clear();
+
+    let installed = result.map_err(|e| match e.downcast_ref::<CodedError>() {
+        Some(_) => e,
+        None => output::coded(ErrorCode::WakatimeCliInstallFailed, e.to_string()),
+    })?;
+
+    if output::is_json() {
+        output::emit("wakatime-cli install", &installed);
+        return Ok(());
+    }
+
+    println!(
+        "{} {} {} in {}",
+        "✔".green().bold(),
+        "wakatime-cli".green(),
+        installed.version.as_deref().unwrap_or("(unknown version)"),
+        config::display_path(&installed.path)
+    );
+    if !installed.downloaded {
+        println!(
+            "  {}",
+            "It was already installed. Pass --force to download the latest release.".dimmed()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,548 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::{Result, eyre::ContextCompat};
+use colored::Colorize;
+use ini::{EscapePolicy, Ini, ParseOption, WriteOption};
+use inkjet::{
+    Highlighter, Language,
+    formatter::Terminal,
+    theme::{Theme, vendored},
+};
+use rand::Rng;
+use serde_json::{Map, Value};
+use termcolor::{ColorChoice, StandardStream};
+
+use crate::output::{self, ErrorCode, say};
+use crate::{api_key, prompt};
+
+const HELP_COMMENT: &str = "# help with config: https://github.com/wakatime/wakatime-cli/blob/develop/USAGE.md#ini-config-file";
+
+/// Keys setup only fills in when the existing config doesn't already set them.
+pub const DEFAULT_ONLY_KEYS: &[&str] = &["heartbeat_rate_limit_seconds", "exclude_unknown_project"];
+
+fn home_dir() -> Result<PathBuf> {
+    dirs::home_dir().wrap_err("Could not find home directory")
+}
+
+/// `$WAKATIME_HOME`, if set. wakatime-cli ignores it when it's empty.
+fn wakatime_home() -> Option<PathBuf> {
+    std::env::var_os("WAKATIME_HOME")
+        .filter(|v| !v.is_empty())
+        .map(|v| expand_tilde(Path::new(&v)))
+}
+
+fn expand_tilde(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Resolves the config file the same way wakatime-cli does: an explicit
+/// `--config` wins, then `$WAKATIME_HOME/.wakatime.cfg`, then `~/.wakatime.cfg`.
+pub fn config_path(override_path: Option<&Path>) -> Result<PathBuf> {
+    if let Some(path) = override_path {
+        return Ok(expand_tilde(path));
+    }
+    match wakatime_home() {
+        Some(home) => Ok(home.join(".wakatime.cfg")),
+        None => Ok(home_dir()?.join(".wakatime.cfg")),
+    }
+}
+
+/// The folder where wakatime-cli and the plugins keep their own state:
+/// `.wakatime` under `$WAKATIME_HOME` when set, otherwise `~/.wakatime`.
+pub fn wakatime_dir() -> Result<PathBuf> {
+    match wakatime_home() {
+        Some(home) => Ok(home.join(".wakatime")),
+        None => Ok(home_dir()?.join(".wakatime")),
+    }
+}
+
+/// Shortens paths under the home directory to `~/...` for display.
+pub fn display_path(path: &Path) -> String {
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rest) => Path::new("~").join(rest).display().to_string(),
+        None => path.display().to_string(),
+    }
+}
+
+fn generate_random_hostname() -> String {
+    let mut rng = rand::rng();
+    (0..6)
+        .map(|_| rng.random_range(b'A'..=b'Z') as char)
+        .collect::<String>()
+}
+
+/// Answers to the advanced setup questions. `None` means ask, or fall back
+/// to the default when running with `--yes`.
+#[derive(Default)]
+pub struct PrivacyOptions {
+    pub hide_branch_names: Option<bool>,
+    pub anonymize_hostname: Option<bool>,
+}
+
+pub fn build_config(
+    api_key: &str,
+    api_url: &str,
+    advanced: bool,
+    privacy: &PrivacyOptions,
+    extra_settings: &[(String, String)],
+    assume_yes: bool,
+    existing: Option<&Ini>,
+) -> Result<Ini> {
+    let mut conf = Ini::new();
+
+    conf.with_section(Some("settings"))
+        .set("api_url", api_url)
+        .set("api_key", api_key)
+        .set("heartbeat_rate_limit_seconds", "30")
+        .set("exclude_unknown_project", "true");
+
+    for (key, value) in extra_settings {
+        conf.with_section(Some("settings")).set(key, value);
+    }
+
+    let branch_hidden_before = existing
+        .and_then(|c| c.get_from(Some("settings"), "hide_branch_names"))
+        .is_some_and(|v| v.trim() != "false");
+
+    let hide_branch = match privacy.hide_branch_names {
+        Some(hide) => Some(hide),
+        None if advanced => Some(prompt::confirm(
+            "Hide branch names?",
+            branch_hidden_before,
+            assume_yes,
+        )?),
+        None => None,
+    };
+
+    let anonymize_hostname = match privacy.anonymize_hostname {
+        Some(anonymize) => anonymize,
+        None if advanced => prompt::confirm("Anonymize your machine name?", false, assume_yes)?,
+        None => false,
+    };
+
+    match hide_branch {
+        Some(true) => {
+            conf.with_section(Some("settings"))
+                .set("hide_branch_names", "true");
+        }
+        Some(false) if branch_hidden_before => {
+            conf.with_section(Some("settings"))
+                .set("hide_branch_names", "false");
+        }
+        _ => {}
+    }
+
+    if anonymize_hostname {
+        let hostname = generate_random_hostname();
+        conf.with_section(Some("settings"))
+            .set("hostname", &hostname);
+        say!("{} {}", "Generated hostname:".dimmed(), hostname.cyan());
+    }
+
+    Ok(conf)
+}
+
+/// Parses a config the way wakatime-cli reads it: no quoting or escapes, and
+/// indented lines continue the previous value.
+pub fn parse_config(contents: &str) -> Result<Ini> {
+    let opt = ParseOption {
+        enabled_quote: false,
+        enabled_escape: false,
+        enabled_indented_mutiline_value: true,
+        ..Default::default()
+    };
+    Ini::load_from_str_opt(contents, opt)
+        .map_err(|e| output::coded(ErrorCode::ConfigInvalid, format!("Invalid config: {e}")))
+}
+
+/// Multi-line values (starting with a newline) continue on indented lines.
+fn format_key_value(key: &str, value: &str) -> String {
+    if value.starts_with('\n') {
+        format!("{key} ={value}")
+    } else {
+        format!("{key} = {value}")
+    }
+}
+
+/// Renders a brand new config file from scratch.
+pub fn render_config(conf: &Ini) -> Result<String> {
+    // wakatime-cli doesn't unescape values, so exclude regexes must stay as-is
+    let write_opt = WriteOption {
+        escape_policy: EscapePolicy::Nothing,
+        kv_separator: " = ",
+        ..Default::default()
+    };
+
+    let mut config_string = Vec::new();
+    conf.write_to_opt(&mut config_string, write_opt)?;
+    config_string.extend_from_slice(HELP_COMMENT.as_bytes());
+    config_string.push(b'\n');
+    Ok(String::from_utf8(config_string)?)
+}
+
+/// Applies the keys in `conf` on top of an existing config file, leaving every
+/// other section, key and comment exactly as it was. Keys listed in
+/// `keep_existing` are only added, never overwritten.
+pub fn merge_config(existing: &str, conf: &Ini, keep_existing: &[&str]) -> String {
+    let mut lines: Vec<String> = existing.lines().map(String::from).collect();
+
+    for (section, props) in conf.iter() {
+        for (key, value) in props.iter() {
+            let new_line = format_key_value(key, value);
+
+            let Some((start, end)) = section_bounds(&lines, section) else {
+                if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                    lines.push(String::new());
+                }
+                if let Some(name) = section {
+                    lines.push(format!("[{name}]"));
+                }
+                lines.push(new_line);
+                continue;
+            };
+
+            match (start..end).find(|&i| key_name(&lines[i]) == Some(key)) {
+                Some(_) if keep_existing.contains(&key) => {}
+                Some(i) => {
+                    let continuation = lines[i + 1..end]
+                        .iter()
+                        .take_while(|l| is_continuation(l))
+                        .count();
+                    lines.splice(i..=i + continuation, [new_line]);
+                }
+                None => {
+                    // Comments right above the next header usually belong to it.
+                    let insert_at = (start..end)
+                        .rev()
+                        .find(|&i| key_name(&lines[i]).is_some() || is_continuation(&lines[i]))
+                        .map_or(start, |i| i + 1);
+                    lines.insert(insert_at, new_line);
+                }
+            }
+        }
+    }
+
+    let mut merged = lines.join("\n");
+    merged.push('\n');
+    merged
+}
+
+/// Replaces the API key in `contents` with its masked form, for display.
+pub fn mask_api_key(contents: &str) -> Result<String> {
+    let conf = parse_config(contents)?;
+    Ok(match conf.get_from(Some("settings"), "api_key") {
+        Some(key) if !key.is_empty() => {
+            let mut masked = Ini::new();
+            masked
+                .with_section(Some("settings"))
+                .set("api_key", api_key::mask_api_key(key));
+            merge_config(contents, &masked, &[])
+        }
+        _ => contents.to_string(),
+    })
+}
+
+/// The config's values as JSON, one object per section.
+pub fn sections_json(contents: &str) -> Result<Map<String, Value>> {
+    let mut sections = Map::new();
+    for (section, props) in parse_config(contents)?.iter() {
+        if section.is_none() && props.is_empty() {
+            continue;
+        }
+        let values: Map<String, Value> = props
+            .iter()
+            .map(|(k, v)| (k.to_string(), Value::from(v)))
+            .collect();
+        sections.insert(section.unwrap_or("default").to_string(), values.into());
+    }
+    Ok(sections)
+}
+
+/// Appends the link to wakatime-cli's config docs unless it's already there.
+pub fn with_help_comment(mut contents: String) -> String {
+    if contents.lines().any(|l| l.trim() == HELP_COMMENT) {
+        return contents;
+    }
+    if contents
+        .lines()
+        .last()
+        .is_some_and(|l| !l.trim().is_empty())
+    {
+        contents.push('\n');
+    }
+    contents.push_str(HELP_COMMENT);
+    contents.push('\n');
+    contents
+}
+
+/// Removes `keys` (and their continuation lines) from `section`, leaving
+/// everything else untouched.
+pub fn remove_keys(existing: &str, section: Option<&str>, keys: &[&str]) -> String {
+    let lines: Vec<String> = existing.lines().map(String::from).collect();
+    let Some((start, end)) = section_bounds(&lines, section) else {
+        return existing.to_string();
+    };
+
+    let mut kept = Vec::with_capacity(lines.len());
+    let mut removing = false;
+    for (i, line) in lines.into_iter().enumerate() {
+        if (start..end).contains(&i) {
+            if let Some(key) = key_name(&line) {
+                removing = keys.contains(&key);
+            } else if !is_continuation(&line) {
+                removing = false;
+            }
+            if removing {
+                continue;
+            }
+        }
+        kept.push(line);
+    }
+
+    let mut result = kept.join("\n");
+    result.push('\n');
+    result
+}
+
+/// Returns the range of lines belonging to `section`, header excluded.
+/// `None` means the keys before the first section header.
+fn section_bounds(lines: &[String], section: Option<&str>) -> Option<(usize, usize)> {
+    let start = match section {
+        None => 0,
+        Some(name) => lines.iter().position(|l| section_name(l) == Some(name))? + 1,
+    };
+    let end = lines[start..]
+        .iter()
+        .position(|l| section_name(l).is_some())
+        .map_or(lines.len(), |i| start + i);
+    Some((start, end))
+}
+
+pub fn section_name(line: &str) -> Option<&str> {
+    let line = line.trim();
+    line.strip_prefix('[')?.strip_suffix(']').map(str::trim)
+}
+
+fn key_name(line: &str) -> Option<&str> {
+    if is_continuation(line) {
+        return None;
+    }
+    let line = line.trim();
+    if line.is_empty() || line.starts_with(['#', ';']) {
+        return None;
+    }
+    line.split_once(['=', ':']).map(|(k, _)| k.trim())
+}
+
+/// wakatime-cli allows multi-line values (e.g. `exclude`) as indented lines.
+fn is_continuation(line: &str) -> bool {
+    line.starts_with([' ', '\t']) && !line.trim().is_empty()
+}
+
+pub fn ini_theme() -> Result<Theme> {
+    Ok(Theme::from_helix(vendored::AYU_DARK)?)
+}
+
+/// Writes one syntax-highlighted INI line to stdout, without a newline.
+pub fn highlight_ini_line(highlighter: &mut Highlighter, theme: &Theme, line: &str) -> Result<()> {
+    let stream = StandardStream::stdout(ColorChoice::Always);
+    let formatter = Terminal::new(theme.clone(), stream);
+    highlighter.highlight_to_writer(Language::Ini, &formatter, line, &mut std::io::sink())?;
+    Ok(())
+}
+
+pub fn print_ini(ini: &str) -> Result<()> {
+    let mut highlighter = Highlighter::new();
+    let theme = ini_theme()?;
+
+    let term_width = terminal_size::terminal_size().map_or(80, |(w, _)| w.0 as usize);
+    let content_width = term_width.saturating_sub(4).max(20);
+    let border_width = content_width + 2;
+
+    println!("┌{}┐", "─".repeat(border_width));
+
+    for line in ini.lines() {
+        let chars: Vec<char> = line.chars().collect();
+        let chunks: Vec<String> = if chars.is_empty() {
+            vec![String::new()]
+        } else {
+            chars
+                .chunks(content_width)
+                .map(|c| c.iter().collect())
+                .collect()
+        };
+
+        for chunk in chunks {
+            print!("│ ");
+            highlight_ini_line(&mut highlighter, &theme, &chunk)?;
+            let padding = content_width - chunk.chars().count();
+            println!("{} │", " ".repeat(padding));
+        }
+    }
+
+    println!("└{}┘", "─".repeat(border_width));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(pairs: &[(&str, &str)]) -> Ini {
+        let mut conf = Ini::new();
+        for (key, value) in pairs {
+            conf.with_section(Some("settings")).set(*key, *value);
+        }
+        conf
+    }
+
+    #[test]
+    fn merge_replaces_keys_and_keeps_everything_else() {
+        let existing = "\
+# my config
+[settings]
+api_key = old
+debug = true
+
+[git]
+submodules_disabled = true
+";
+        let merged = merge_config(existing, &settings(&[("api_key", "new")]), &[]);
+        assert_eq!(
+            merged,
+            "\
+# my config
+[settings]
+api_key = new
+debug = true
+
+[git]
+submodules_disabled = true
+"
+        );
+    }
+
+    #[test]
+    fn merge_replaces_continuation_lines_with_the_key() {
+        let existing = "\
+[settings]
+exclude =
+    ^/tmp/
+    \\.env$
+debug = true
+";
+        let merged = merge_config(existing, &settings(&[("exclude", "\n    ^/private/")]), &[]);
+        assert_eq!(
+            merged,
+            "\
+[settings]
+exclude =
+    ^/private/
+debug = true
+"
+        );
+    }
+
+    #[test]
+    fn merge_adds_missing_keys_after_the_last_one_in_the_section() {
+        let existing = "\
+[settings]
+debug = true
+
+# comments for the next section
+[git]
+submodules_disabled = true
+";
+        let merged = merge_config(existing, &settings(&[("api_key", "new")]), &[]);
+        assert_eq!(
+            merged,
+            "\
+[settings]
+debug = true
+api_key = new
+
+# comments for the next section
+[git]
+submodules_disabled = true
+"
+        );
+    }
+
+    #[test]
+    fn merge_adds_a_missing_section_at_the_end() {
+        let existing = "\
+[git]
+submodules_disabled = true
+";
+        let merged = merge_config(existing, &settings(&[("api_key", "new")]), &[]);
+        assert_eq!(
+            merged,
+            "\
+[git]
+submodules_disabled = true
+
+[settings]
+api_key = new
+"
+        );
+    }
+
+    #[test]
+    fn merge_only_adds_keep_existing_keys() {
+        let existing = "\
+[settings]
+heartbeat_rate_limit_seconds = 120
+";
+        let conf = settings(&[
+            ("heartbeat_rate_limit_seconds", "30"),
+            ("exclude_unknown_project", "true"),
+        ]);
+        let merged = merge_config(existing, &conf, DEFAULT_ONLY_KEYS);
+        assert_eq!(
+            merged,
+            "\
+[settings]
+heartbeat_rate_limit_seconds = 120
+exclude_unknown_project = true
+"
+        );
+    }
+
+    #[test]
+    fn remove_drops_keys_and_their_continuation_lines() {
+        let existing = "\
+[settings]
+api_key = secret
+exclude =
+    ^/tmp/
+debug = true
+
+[other]
+api_key = kept
+";
+        let removed = remove_keys(existing, Some("settings"), &["api_key", "exclude"]);
+        assert_eq!(
+            removed,
+            "\
+[settings]
+debug = true
+
+[other]
+api_key = kept
+"
+        );
+    }
+
+    #[test]
+    fn remove_leaves_a_config_without_the_section_alone() {
+        let existing = "[git]\nsubmodules_disabled = true\n";
+        assert_eq!(
+            remove_keys(existing, Some("settings"), &["api_key"]),
+            existing
+        );
+    }
+}
@@ -0,0 +1,329 @@
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use colored::Colorize;
+use reqwest::{StatusCode, Url};
+use rustls::client::WebPkiServerVerifier;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{
+    ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+use serde::Serialize;
+
+use crate::http;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Answers 204 with an empty body unless a captive portal is in the way.
+const CAPTIVE_PORTAL_CHECK: &str = "http://connectivitycheck.gstatic.com/generate_204";
+
+/// One step of [`diagnose`], with a plain-language explanation.
+#[derive(Serialize)]
+pub struct Step {
+    pub name: &'static str,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl Step {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            ok: true,
+            detail: detail.into(),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            ok: false,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Works out why requests to `api_url` fail by checking DNS, the TCP
+/// connection, the TLS certificate, the HTTP response and whether a captive
+/// portal is in the way, stopping at the first step that can't succeed.
+pub fn diagnose(api_url: &str) -> Vec<Step> {
+    let mut steps = Vec::new();
+
+    let Ok(url) = Url::parse(api_url) else {
+        steps.push(Step::fail(
+            "URL",
+            format!("{api_url} isn't a valid URL. Check api_url in your config."),
+        ));
+        return steps;
+    };
+    let host = url.host_str().unwrap_or_default().to_string();
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    // Through a proxy, only the proxy's view of the network matters
+    let proxy = http::settings().proxy;
+    if proxy.is_none() {
+        let Some(addr) = check_dns(&host, port, &mut steps) else {
+            return steps;
+        };
+        let Some(tcp) = check_tcp(addr, &host, &mut steps) else {
+            return steps;
+        };
+        if url.scheme() == "https" {
+            check_tls(tcp, &host, &mut steps);
+        }
+    }
+
+    check_http(api_url, &host, proxy.as_deref(), &mut steps);
+    check_captive_portal(&mut steps);
+
+    steps
+}
+
+fn check_dns(host: &str, port: u16, steps: &mut Vec<Step>) -> Option<SocketAddr> {
+    match (host, port).to_socket_addrs().map(|mut a| a.next()) {
+        Ok(Some(addr)) => {
+            steps.push(Step::ok("DNS", format!("{host} resolves to {}", addr.ip())));
+            Some(addr)
+        }
+        Ok(None) | Err(_) => {
+            steps.push(Step::fail(
+                "DNS",
+                format!(
+                    "Couldn't look up {host}. You may be offline, or your network's DNS may be blocking it."
+                ),
+            ));
+            None
+        }
+    }
+}
+
+fn check_tcp(addr: SocketAddr, host: &str, steps: &mut Vec<Step>) -> Option<TcpStream> {
+    let started = Instant::now();
+    match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+        Ok(tcp) => {
+            steps.push(Step::ok(
+                "TCP",
+                format!(
+                    "Connected to {host}:{} in {}ms",
+                    addr.port(),
+                    started.elapsed().as_millis()
+                ),
+            ));
+            Some(tcp)
+        }
+        Err(e) => {
+            steps.push(Step::fail(
+                "TCP",
+                format!(
+                    "Couldn't connect to {host}:{} ({e}). A firewall may be blocking it; school networks often need a proxy (--proxy).",
+                    addr.port()
+                ),
+            ));
+            None
+        }
+    }
+}
+
+fn check_tls(mut tcp: TcpStream, host: &str, steps: &mut Vec<Step>) {
+    let verifier = match RecordingVerifier::new(http::settings().ssl_certs_file.as_deref()) {
+        Ok(verifier) => Arc::new(verifier),
+        Err(e) => {
+            steps.push(Step::fail("TLS", format!("Couldn't set up TLS: {e}")));
+            return;
+        }
+    };
+    let config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map(|b| {
+                b.dangerous()
+                    .with_custom_certificate_verifier(verifier.clone())
+                    .with_no_client_auth()
+            });
+
+    let handshake = config
+        .map_err(|e| e.to_string())
+        .and_then(|config| {
+            let name = ServerName::try_from(host.to_string()).map_err(|e| e.to_string())?;
+            ClientConnection::new(Arc::new(config), name).map_err(|e| e.to_string())
+        })
+        .and_then(|mut conn| {
+            let _ = tcp.set_read_timeout(Some(CONNECT_TIMEOUT));
+            let _ = tcp.set_write_timeout(Some(CONNECT_TIMEOUT));
+            while conn.is_handshaking() {
+                conn.complete_io(&mut tcp).map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        });
+
+    let seen = verifier.seen.lock().unwrap().take();
+    match (handshake, seen) {
+        (_, Some((issuer, Ok(())))) => {
+            steps.push(Step::ok("TLS", format!("Certificate issued by {issuer}")));
+        }
+        (_, Some((issuer, Err(e)))) => steps.push(Step::fail(
+            "TLS",
+            format!(
+                "The certificate for {host} was issued by {issuer}, which isn't trusted ({e}). Your network is probably intercepting HTTPS: ask for its CA certificate and pass it with --ssl-certs-file."
+            ),
+        )),
+        (Err(e), None) => steps.push(Step::fail(
+            "TLS",
+            format!("The TLS handshake with {host} failed ({e}). Something on your network may be interfering with HTTPS."),
+        )),
+        (Ok(()), None) => {}
+    }
+}
+
+fn check_http(api_url: &str, host: &str, proxy: Option<&str>, steps: &mut Vec<Step>) {
+    let via = proxy.map(|p| format!(" through {p}")).unwrap_or_default();
+    let response = http::client().and_then(|c| c.get(format!("{api_url}/users/current")).send());
+
+    match response {
+        Ok(r) if r.url().host_str() != Some(host) => steps.push(Step::fail(
+            "HTTP",
+            format!(
+                "Requests to {host}{via} were redirected to {}. You're probably behind a Wi-Fi login page: open a browser and sign in first.",
+                r.url().host_str().unwrap_or_default()
+            ),
+        )),
+        Ok(r) if r.status().is_server_error() => steps.push(Step::fail(
+            "HTTP",
+            format!(
+                "{host}{via} answered HTTP {}. The server is having trouble; try again in a few minutes.",
+                r.status()
+            ),
+        )),
+        Ok(r) => steps.push(Step::ok(
+            "HTTP",
+            format!("{host}{via} answered HTTP {}", r.status()),
+        )),
+        Err(e) => steps.push(Step::fail(
+            "HTTP",
+            format!("The request to {host}{via} failed: {e}"),
+        )),
+    }
+}
+
+fn check_captive_portal(steps: &mut Vec<Step>) {
+    let Ok(response) = http::client().and_then(|c| c.get(CAPTIVE_PORTAL_CHECK).send()) else {
+        // Blocked or offline, which the steps above already explain
+        return;
+    };
+
+    if response.status() == StatusCode::NO_CONTENT {
+        steps.push(Step::ok("Captive portal", "None detected"));
+    } else {
+        steps.push(Step::fail(
+            "Captive portal",
+            "Your network is showing a login page instead of the internet. Open a browser and sign in to the Wi-Fi, then run setup again.",
+        ));
+    }
+}
+
+/// Prints the steps of a diagnosis.
+pub fn print(steps: &[Step]) {
+    eprintln!("\n{}", "Network diagnostics:".bold());
+    for step in steps {
+        let icon = if step.ok { "✔".green() } else { "✘".red() };
+        eprintln!("{} {}: {}", icon.bold(), step.name, step.detail);
+    }
+}
+
+/// Checks the server certificate like any client would, but lets the
+/// handshake finish either way so we can report who issued it.
+#[derive(Debug)]
+struct RecordingVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    seen: Mutex<Option<(String, Result<(), rustls::Error>)>>,
+}
+
+impl RecordingVerifier {
+    fn new(ssl_certs_file: Option<&Path>) -> Result<Self, String> {
+        let mut roots = RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        if let Some(path) = ssl_certs_file {
+            let certs = CertificateDer::pem_file_iter(path)
+                .map_err(|e| e.to_string())?
+                .flatten();
+            roots.add_parsable_certificates(certs);
+        }
+
+        let inner = WebPkiServerVerifier::builder_with_provider(
+            Arc::new(roots),
+            Arc::new(rustls::crypto::ring::default_provider()),
+        )
+        .build()
+        .map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            inner,
+            seen: Mutex::new(None),
+        })
+    }
+}
+
+impl ServerCertVerifier for RecordingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let result = self
+            .inner
+            .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+            .map(|_| ());
+        *self.seen.lock().unwrap() = Some((issuer(end_entity), result));
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// The issuer's organization and common name, e.g. "Let's Encrypt (R11)".
+fn issuer(cert: &CertificateDer<'_>) -> String {
+    let Ok((_, cert)) = x509_parser::parse_x509_certificate(cert) else {
+        return "an unreadable certificate".to_string();
+    };
+    let issuer = cert.issuer();
+    let org = issuer
+        .iter_organization()
+        .next()
+        .and_then(|o| o.as_str().ok());
+    let cn = issuer
+        .iter_common_name()
+        .next()
+        .and_then(|c| c.as_str().ok());
+    match (org, cn) {
+        (Some(org), Some(cn)) => format!("{org} ({cn})"),
+        (Some(name), None) | (None, Some(name)) => name.to_string(),
+        (None, None) => issuer.to_string(),
+    }
+}
@@ -0,0 +1,116 @@
+use color_eyre::Result;
+use colored::Colorize;
+use ini::Ini;
+use inkjet::Highlighter;
+use similar::{ChangeTag, TextDiff};
+
+use crate::config;
+
+/// Prints a line diff from `old` to `new` with INI highlighting, followed by a
+/// per-section summary of which keys are added, removed and changed.
+pub fn print_config_diff(old: &str, new: &str) -> Result<()> {
+    let diff = TextDiff::from_lines(old, new);
+
+    if diff.ratio() == 1.0 {
+        println!("{}", "No changes.".dimmed());
+        return Ok(());
+    }
+
+    let mut highlighter = Highlighter::new();
+    let theme = config::ini_theme()?;
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    for group in diff.grouped_ops(3) {
+        let first_new_line = group.first().map_or(0, |op| op.new_range().start);
+        let section = new_lines[..first_new_line.min(new_lines.len())]
+            .iter()
+            .rev()
+            .find_map(|l| config::section_name(l));
+        match section {
+            Some(name) => println!("{}", format!("@@ [{name}] @@").cyan()),
+            None => println!("{}", "@@".cyan()),
+        }
+
+        for op in group {
+            for change in diff.iter_changes(&op) {
+                let line = change.value().trim_end_matches(['\r', '\n']);
+                match change.tag() {
+                    ChangeTag::Equal => println!("{}", format!("  {line}").dimmed()),
+                    ChangeTag::Delete => {
+                        print!("{} ", "-".red().bold());
+                        config::highlight_ini_line(&mut highlighter, &theme, line)?;
+                        println!();
+                    }
+                    ChangeTag::Insert => {
+                        print!("{} ", "+".green().bold());
+                        config::highlight_ini_line(&mut highlighter, &theme, line)?;
+                        println!();
+                    }
+                }
+            }
+        }
+    }
+
+    if let (Ok(old_ini), Ok(new_ini)) = (config::parse_config(old), config::parse_config(new)) {
+        print_key_summary(&old_ini, &new_ini);
+    }
+
+    Ok(())
+}
+
+fn print_key_summary(old: &Ini, new: &Ini) {
+    let mut sections: Vec<Option<&str>> = old.sections().chain(new.sections()).collect();
+    sections.sort();
+    sections.dedup();
+
+    let mut printed_header = false;
+    for section in sections {
+        let old_props = old.section(section);
+        let new_props = new.section(section);
+        let old_get = |k: &str| old_props.and_then(|p| p.get(k));
+        let new_get = |k: &str| new_props.and_then(|p| p.get(k));
+
+        let mut added = Vec::new();
+        let mut changed = Vec::new();
+        let mut removed = Vec::new();
+
+        for (key, value) in new_props.into_iter().flat_map(|p| p.iter()) {
+            match old_get(key) {
+                None => added.push(key),
+                Some(old_value) if old_value != value => changed.push(key),
+                Some(_) => {}
+            }
+        }
+        for (key, _) in old_props.into_iter().flat_map(|p| p.iter()) {
+            if new_get(key).is_none() {
+                removed.push(key);
+            }
+        }
+
+        if added.is_empty() && changed.is_empty() && removed.is_empty() {
+            continue;
+        }
+
+        if !printed_header {
+            println!("\n{}", "Summary:".bold());
+            printed_header = true;
+        }
+
+        let name = section.map_or_else(|| "(top level)".to_string(), |s| format!("[{s}]"));
+        let mut parts = Vec::new();
+        if !added.is_empty() {
+            parts.push(format!("added {}", added.join(", ")).green().to_string());
+        }
+        if !changed.is_empty() {
+            parts.push(
+                format!("changed {}", changed.join(", "))
+                    .yellow()
+                    .to_string(),
+            );
+        }
+        if !removed.is_empty() {
+            parts.push(format!("removed {}", removed.join(", ")).red().to_string());
+        }
+        println!("  {} {}", name.cyan(), parts.join("; "));
+    }
+}
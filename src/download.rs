@@ -0,0 +1,743 @@
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::Utc;
+use color_eyre::{Report, Result, eyre::eyre};
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::StatusCode;
+use reqwest::blocking::Response;
+use reqwest::header::{
+    ETAG, HeaderName, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, RANGE, USER_AGENT,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::cache::{self, Entry};
+use crate::output::{self, ErrorCode};
+use crate::plan::Action;
+use crate::{bundle, http};
+
+const USER_AGENT_VALUE: &str = concat!("hackatime-setup/", env!("CARGO_PKG_VERSION"));
+
+/// Installers are several megabytes, so allow far longer than an API call.
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Something setup downloads to install, and where its expected SHA-256
+/// comes from. Nothing is returned from [`fetch`] until the download matches
+/// it, so nothing unverified gets extracted or run, bar the
+/// [`Artifact::Unchecked`] downloads a bundle pins itself.
+pub enum Artifact {
+    /// `url`, listed in a `sha256sum`-style file published next to it.
+    Checksummed {
+        name: String,
+        url: String,
+        checksums_url: String,
+    },
+    /// An asset of the latest GitHub release of `repo`, checked against the
+    /// digest GitHub records when it's uploaded.
+    GithubRelease {
+        repo: &'static str,
+        asset: &'static str,
+    },
+    /// The latest version of an extension on Open VSX, checked against the
+    /// SHA-256 it publishes for each file.
+    OpenVsx {
+        namespace: &'static str,
+        extension: &'static str,
+    },
+    /// A download with no published digest. Only bundles fetch these: the
+    /// bundle's manifest then pins the digest for every install from it.
+    Unchecked {
+        name: &'static str,
+        url: &'static str,
+    },
+}
+
+/// The downloaded bytes, once they've matched the expected digest.
+pub struct Verified {
+    pub bytes: Vec<u8>,
+    pub sha256: String,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Deserialize)]
+struct ReleaseAsset {
+    name: String,
+    browser_download_url: String,
+    digest: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OpenVsxExtension {
+    files: OpenVsxFiles,
+}
+
+#[derive(Deserialize)]
+struct OpenVsxFiles {
+    download: String,
+    sha256: Option<String>,
+}
+
+impl Artifact {
+    /// The file name it's known by, in bundles too.
+    pub fn name(&self) -> String {
+        match self {
+            Artifact::Checksummed { name, .. } => name.clone(),
+            Artifact::GithubRelease { asset, .. } => asset.to_string(),
+            Artifact::OpenVsx {
+                namespace,
+                extension,
+            } => format!("{namespace}.{extension}.vsix"),
+            Artifact::Unchecked { name, .. } => name.to_string(),
+        }
+    }
+
+    /// Whether there's a published digest to check it against.
+    pub fn is_checked(&self) -> bool {
+        !matches!(self, Artifact::Unchecked { .. })
+    }
+
+    /// Describes downloading the artifact to `destination`.
+    pub fn plan(&self, destination: impl Into<PathBuf>) -> Vec<Action> {
+        let destination = destination.into();
+        if let Some(bundle) = bundle::active() {
+            return vec![Action::FromBundle {
+                bundle: bundle.path.clone(),
+                file: self.name(),
+                destination,
+            }];
+        }
+
+        let (checksum_request, url) = match self {
+            Artifact::Checksummed {
+                url, checksums_url, ..
+            } => (Some(checksums_url.clone()), url.clone()),
+            Artifact::GithubRelease { repo, asset } => (
+                Some(release_url(repo)),
+                format!("https://github.com/{repo}/releases/latest/download/{asset}"),
+            ),
+            Artifact::OpenVsx {
+                namespace,
+                extension,
+            } => (
+                Some(open_vsx_url(namespace, extension)),
+                format!(
+                    "{}/$VERSION/file/{namespace}.{extension}-$VERSION.vsix",
+                    open_vsx_url(namespace, extension)
+                ),
+            ),
+            Artifact::Unchecked { url, .. } => (None, url.to_string()),
+        };
+        checksum_request
+            .map(|url| Action::HttpRequest {
+                method: "GET",
+                url,
+                description: format!("look up the SHA-256 of {}", self.name()),
+            })
+            .into_iter()
+            .chain([Action::Download { url, destination }])
+            .collect()
+    }
+}
+
+/// Downloads `artifact` and checks it against its published SHA-256,
+/// refusing it if the source has no digest or it doesn't match (only
+/// [`Artifact::Unchecked`] skips this). During
+/// `--from-bundle` setup it comes from the bundle instead, checked against
+/// the bundle's manifest.
+pub fn fetch(artifact: &Artifact) -> Result<Verified> {
+    let name = artifact.name();
+    if let Some(bundle) = bundle::active() {
+        return bundle.read(&name);
+    }
+
+    let (url, expected) = match artifact {
+        Artifact::Checksummed {
+            url, checksums_url, ..
+        } => (url.clone(), Some(listed_checksum(checksums_url, &name)?)),
+        Artifact::GithubRelease { repo, asset } => {
+            let (url, expected) = github_asset(&release_url(repo), repo, asset)?;
+            (url, Some(expected))
+        }
+        Artifact::OpenVsx {
+            namespace,
+            extension,
+        } => {
+            let (url, expected) = open_vsx_file(&open_vsx_url(namespace, extension), &name)?;
+            (url, Some(expected))
+        }
+        Artifact::Unchecked { url, .. } => (url.to_string(), None),
+    };
+
+    let bytes = get_cached(&url, &name)?;
+    let sha256 = format!("{:x}", Sha256::digest(&bytes));
+    if let Some(expected) = expected.filter(|expected| *expected != sha256) {
+        // Don't hand the same bad bytes back next time
+        Entry::for_url(&url)?.remove();
+        return Err(mismatch(&name, &expected, &sha256));
+    }
+
+    Ok(Verified { bytes, sha256 })
+}
+
+/// Looks `name` up in a `sha256sum`-style file.
+fn listed_checksum(checksums_url: &str, name: &str) -> Result<String> {
+    let checksums = get(checksums_url)?;
+    String::from_utf8_lossy(&checksums)
+        .lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(_, file)| file.trim().trim_start_matches('*') == name)
+        .map(|(digest, _)| digest.to_lowercase())
+        .ok_or_else(|| eyre!("{} doesn't list a checksum for {}", checksums_url, name))
+}
+
+/// The download URL and SHA-256 of `asset` in the release at `release_url`.
+fn github_asset(release_url: &str, repo: &str, asset: &str) -> Result<(String, String)> {
+    let release: Release = serde_json::from_slice(&get(release_url)?)
+        .map_err(|e| eyre!("Couldn't read the latest {} release: {}", repo, e))?;
+    let found = release
+        .assets
+        .into_iter()
+        .find(|a| a.name == asset)
+        .ok_or_else(|| eyre!("The latest {} release has no {}", repo, asset))?;
+    let expected = found
+        .digest
+        .as_deref()
+        .and_then(|d| d.strip_prefix("sha256:"))
+        .map(str::to_lowercase)
+        .ok_or_else(|| {
+            eyre!(
+                "GitHub has no SHA-256 for {}, so I can't check it and won't install it",
+                asset
+            )
+        })?;
+    Ok((found.browser_download_url, expected))
+}
+
+/// The download URL and SHA-256 of the latest version of the extension at
+/// `extension_url`.
+fn open_vsx_file(extension_url: &str, name: &str) -> Result<(String, String)> {
+    let info: OpenVsxExtension = serde_json::from_slice(&get(extension_url)?)
+        .map_err(|e| eyre!("Couldn't read {} on Open VSX: {}", name, e))?;
+    let sha256_url = info.files.sha256.ok_or_else(|| {
+        eyre!(
+            "Open VSX has no SHA-256 for {}, so I can't check it and won't install it",
+            name
+        )
+    })?;
+    let expected = String::from_utf8_lossy(&get(&sha256_url)?)
+        .split_whitespace()
+        .next()
+        .map(str::to_lowercase)
+        .ok_or_else(|| eyre!("{} is empty", sha256_url))?;
+    Ok((info.files.download, expected))
+}
+
+pub fn mismatch(name: &str, expected: &str, actual: &str) -> Report {
+    output::coded(
+        ErrorCode::ChecksumMismatch,
+        format!(
+            "{name} doesn't match its published SHA-256 (expected {expected}, got {actual}), so I didn't install it. The download may have been corrupted or tampered with; try again on another network."
+        ),
+    )
+}
+
+fn open_vsx_url(namespace: &str, extension: &str) -> String {
+    format!("https://open-vsx.org/api/{namespace}/{extension}")
+}
+
+fn release_url(repo: &str) -> String {
+    format!("https://api.github.com/repos/{repo}/releases/latest")
+}
+
+fn get(url: &str) -> Result<Vec<u8>> {
+    // GitHub's API turns away requests without a User-Agent
+    let request = http::client()?
+        .get(url)
+        .header(USER_AGENT, USER_AGENT_VALUE)
+        .timeout(DOWNLOAD_TIMEOUT);
+    let response = http::send(request).map_err(|e| eyre!("Failed to download {}: {}", url, e))?;
+    if !response.status().is_success() {
+        return Err(eyre!(
+            "Failed to download {} (HTTP {})",
+            url,
+            response.status()
+        ));
+    }
+    Ok(response
+        .bytes()
+        .map_err(|e| eyre!("Failed to read {}: {}", url, e))?
+        .to_vec())
+}
+
+/// Downloads `url` through the cache: a finished download is reused when the
+/// server says it hasn't changed, and an interrupted one picks up where it
+/// stopped, both now and on the next run.
+fn get_cached(url: &str, name: &str) -> Result<Vec<u8>> {
+    let entry = Entry::for_url(url)?;
+    let retries = http::settings().timeouts.retries;
+
+    let mut attempt = 0;
+    loop {
+        match try_get_cached(url, name, &entry)? {
+            Ok(bytes) => return Ok(bytes),
+            Err(e) if attempt < retries => {
+                attempt += 1;
+                eprintln!(
+                    "{}",
+                    format!(
+                        "Downloading {name} was interrupted ({e}), resuming (attempt {} of {})...",
+                        attempt + 1,
+                        retries + 1
+                    )
+                    .dimmed()
+                );
+            }
+            Err(e) => {
+                return Err(eyre!(
+                    "Failed to download {}: {}. Run this again to pick up where it stopped.",
+                    url,
+                    e
+                ));
+            }
+        }
+    }
+}
+
+/// One go at [`get_cached`]. The inner error means the transfer broke off
+/// part way and is worth resuming.
+fn try_get_cached(url: &str, name: &str, entry: &Entry) -> Result<io::Result<Vec<u8>>> {
+    let part = entry.part_path();
+    let meta = entry.meta();
+    let mut request = http::client()?
+        .get(url)
+        .header(USER_AGENT, USER_AGENT_VALUE)
+        .timeout(DOWNLOAD_TIMEOUT);
+
+    let mut offset = 0;
+    if let Some(meta) = &meta {
+        if entry.path.exists() {
+            if let Some(etag) = &meta.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &meta.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        } else if let (Ok(existing), Some(validator)) = (
+            fs::metadata(&part),
+            meta.etag.as_ref().or(meta.last_modified.as_ref()),
+        ) {
+            // If-Range makes the server send it all again if it changed
+            offset = existing.len();
+            request = request
+                .header(RANGE, format!("bytes={offset}-"))
+                .header(IF_RANGE, validator);
+        }
+    }
+
+    let response = http::send(request).map_err(|e| eyre!("Failed to download {}: {}", url, e))?;
+    match response.status() {
+        StatusCode::NOT_MODIFIED => {
+            return Ok(fs::read(&entry.path));
+        }
+        StatusCode::RANGE_NOT_SATISFIABLE => {
+            entry.remove();
+            return Ok(Err(io::Error::other("the partial download was unusable")));
+        }
+        status if !status.is_success() => {
+            return Err(eyre!("Failed to download {} (HTTP {})", url, status));
+        }
+        _ => {}
+    }
+
+    let resumed = response.status() == StatusCode::PARTIAL_CONTENT;
+    if !resumed {
+        offset = 0;
+        // The old copy is out of date, and must not be revalidated against
+        // the new download's ETag if this one gets interrupted
+        let _ = fs::remove_file(&entry.path);
+        entry.write_meta(&cache::Meta {
+            url: url.to_string(),
+            etag: header(&response, ETAG),
+            last_modified: header(&response, LAST_MODIFIED),
+            fetched_at: Utc::now(),
+        })?;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(&part)
+        .map_err(|e| eyre!("Failed to write {}: {}", part.display(), e))?;
+
+    let pb = output::progress_bar(match response.content_length() {
+        Some(len) => ProgressBar::new(offset + len).with_style(
+            ProgressStyle::with_template(
+                "{msg} [{bar:30}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta} left)",
+            )
+            .unwrap()
+            .progress_chars("=> "),
+        ),
+        None => ProgressBar::new_spinner().with_style(
+            ProgressStyle::with_template("{spinner} {msg} {bytes} ({bytes_per_sec})").unwrap(),
+        ),
+    });
+    pb.set_message(format!("Downloading {name}"));
+    pb.set_position(offset);
+    let copied = io::copy(&mut pb.wrap_read(response), &mut file);
+    pb.finish_and_clear();
+    if let Err(e) = copied {
+        return Ok(Err(e));
+    }
+
+    fs::rename(&part, &entry.path)
+        .map_err(|e| eyre!("Failed to save {}: {}", entry.path.display(), e))?;
+    Ok(fs::read(&entry.path))
+}
+
+fn header(response: &Response, name: HeaderName) -> Option<String> {
+    response
+        .headers()
+        .get(name)?
+        .to_str()
+        .ok()
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, OnceLock};
+
+    use super::*;
+    use crate::output::CodedError;
+    use crate::test_server::{TestServer, reply};
+
+    const BODY: &str = "wakatime-cli build";
+
+    fn sha256(bytes: &str) -> String {
+        format!("{:x}", Sha256::digest(bytes.as_bytes()))
+    }
+
+    /// Serves `checksums` at `/SHA256SUMS` and [`BODY`] at `/tool.zip`.
+    fn checksummed(checksums: String) -> (TestServer, Artifact) {
+        let server = TestServer::start(move |request| match request.path.as_str() {
+            "/SHA256SUMS" => reply(200, &[], &checksums),
+            "/tool.zip" => reply(200, &[], BODY),
+            _ => reply(404, &[], ""),
+        });
+        let artifact = Artifact::Checksummed {
+            name: "tool.zip".to_string(),
+            url: format!("{}/tool.zip", server.url),
+            checksums_url: format!("{}/SHA256SUMS", server.url),
+        };
+        (server, artifact)
+    }
+
+    #[test]
+    fn accepts_a_download_listed_in_the_checksums_file() {
+        let checksums = format!(
+            "{}  other.zip\n{} *tool.zip\n",
+            sha256("other"),
+            sha256(BODY).to_uppercase()
+        );
+        let (_server, artifact) = checksummed(checksums);
+
+        let verified = fetch(&artifact).unwrap();
+        assert_eq!(verified.bytes, BODY.as_bytes());
+        assert_eq!(verified.sha256, sha256(BODY));
+    }
+
+    #[test]
+    fn refuses_a_mismatch_and_drops_it_from_the_cache() {
+        let (server, artifact) = checksummed(format!("{}  tool.zip\n", sha256("tampered")));
+
+        let Err(error) = fetch(&artifact) else {
+            panic!("fetch accepted the download");
+        };
+        let coded = error.downcast_ref::<CodedError>().unwrap();
+        assert_eq!(coded.code, ErrorCode::ChecksumMismatch);
+
+        let entry = Entry::for_url(&format!("{}/tool.zip", server.url)).unwrap();
+        assert!(!entry.path.exists());
+        assert!(!entry.part_path().exists());
+        assert!(entry.meta().is_none());
+    }
+
+    #[test]
+    fn refuses_a_download_the_checksums_file_does_not_list() {
+        let (server, artifact) = checksummed(format!("{}  other.zip\n", sha256(BODY)));
+
+        let Err(error) = fetch(&artifact) else {
+            panic!("fetch accepted the download");
+        };
+        assert!(
+            error
+                .to_string()
+                .contains("doesn't list a checksum for tool.zip")
+        );
+        assert_eq!(server.requests(), ["GET /SHA256SUMS"]);
+    }
+
+    #[test]
+    fn reads_the_digest_github_records_for_an_asset() {
+        let server = TestServer::start(|_| {
+            reply(
+                200,
+                &[],
+                r#"{"assets": [
+                    {"name": "other.zip", "browser_download_url": "https://example.com/other.zip", "digest": "sha256:00"},
+                    {"name": "tool.zip", "browser_download_url": "https://example.com/tool.zip", "digest": "sha256:ABCDEF"}
+                ]}"#,
+            )
+        });
+
+        let (url, expected) = github_asset(&server.url, "owner/repo", "tool.zip").unwrap();
+        assert_eq!(url, "https://example.com/tool.zip");
+        assert_eq!(expected, "abcdef");
+    }
+
+    #[test]
+    fn refuses_a_github_asset_without_a_digest() {
+        let server = TestServer::start(|_| {
+            reply(
+                200,
+                &[],
+                r#"{"assets": [{"name": "tool.zip", "browser_download_url": "https://example.com/tool.zip", "digest": null}]}"#,
+            )
+        });
+
+        let error = github_asset(&server.url, "owner/repo", "tool.zip").unwrap_err();
+        assert!(error.to_string().contains("GitHub has no SHA-256"));
+    }
+
+    #[test]
+    fn reads_the_digest_open_vsx_publishes() {
+        // The extension's info points back at the server for the digest
+        let own_url = Arc::new(OnceLock::<String>::new());
+        let server = TestServer::start({
+            let own_url = Arc::clone(&own_url);
+            move |request| match request.path.as_str() {
+                "/api/ns/ext" => reply(
+                    200,
+                    &[],
+                    &format!(
+                        r#"{{"files": {{"download": "https://example.com/ext.vsix", "sha256": "{}/ext.sha256"}}}}"#,
+                        own_url.get().unwrap()
+                    ),
+                ),
+                _ => reply(200, &[], "ABC123  ns.ext-1.0.0.vsix\n"),
+            }
+        });
+        own_url.set(server.url.clone()).unwrap();
+
+        let (url, expected) =
+            open_vsx_file(&format!("{}/api/ns/ext", server.url), "ns.ext.vsix").unwrap();
+        assert_eq!(url, "https://example.com/ext.vsix");
+        assert_eq!(expected, "abc123");
+        assert_eq!(server.requests(), ["GET /api/ns/ext", "GET /ext.sha256"]);
+    }
+
+    #[test]
+    fn refuses_an_open_vsx_file_without_a_digest() {
+        let server = TestServer::start(|_| {
+            reply(
+                200,
+                &[],
+                r#"{"files": {"download": "https://example.com/ext.vsix"}}"#,
+            )
+        });
+
+        let error = open_vsx_file(&server.url, "ns.ext.vsix").unwrap_err();
+        assert!(error.to_string().contains("Open VSX has no SHA-256"));
+    }
+
+    /// Puts a finished download and/or a partial one for `url` in the cache,
+    /// as if an earlier run had fetched it with ETag `etag`.
+    fn seed(url: &str, etag: &str, finished: Option<&str>, part: Option<&str>) -> Entry {
+        let entry = Entry::for_url(url).unwrap();
+        entry
+            .write_meta(&cache::Meta {
+                url: url.to_string(),
+                etag: Some(etag.to_string()),
+                last_modified: None,
+                fetched_at: Utc::now(),
+            })
+            .unwrap();
+        if let Some(contents) = finished {
+            fs::write(&entry.path, contents).unwrap();
+        }
+        if let Some(contents) = part {
+            fs::write(entry.part_path(), contents).unwrap();
+        }
+        entry
+    }
+
+    fn download(server: &TestServer) -> (String, Entry) {
+        let url = format!("{}/tool.zip", server.url);
+        let bytes = get_cached(&url, "tool.zip").unwrap();
+        (
+            String::from_utf8(bytes).unwrap(),
+            Entry::for_url(&url).unwrap(),
+        )
+    }
+
+    #[test]
+    fn caches_a_download_with_its_validators() {
+        let server = TestServer::start(|_| {
+            reply(
+                200,
+                &[
+                    ("ETag", "\"v1\""),
+                    ("Last-Modified", "Tue, 01 Sep 2026 00:00:00 GMT"),
+                ],
+                "hello world",
+            )
+        });
+
+        let (body, entry) = download(&server);
+        assert_eq!(body, "hello world");
+        assert_eq!(fs::read_to_string(&entry.path).unwrap(), "hello world");
+        assert!(!entry.part_path().exists());
+        let meta = entry.meta().unwrap();
+        assert_eq!(meta.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(
+            meta.last_modified.as_deref(),
+            Some("Tue, 01 Sep 2026 00:00:00 GMT")
+        );
+    }
+
+    #[test]
+    fn reuses_the_cached_copy_when_the_server_says_it_has_not_changed() {
+        let server = TestServer::start(|request| match request.header("If-None-Match") {
+            Some("\"v1\"") => reply(304, &[("ETag", "\"v1\"")], ""),
+            _ => reply(200, &[("ETag", "\"v2\"")], "changed"),
+        });
+        seed(
+            &format!("{}/tool.zip", server.url),
+            "\"v1\"",
+            Some("cached"),
+            None,
+        );
+
+        let (body, entry) = download(&server);
+        assert_eq!(body, "cached");
+        assert_eq!(entry.meta().unwrap().etag.as_deref(), Some("\"v1\""));
+    }
+
+    #[test]
+    fn replaces_the_cached_copy_when_it_has_changed() {
+        let server = TestServer::start(|_| reply(200, &[("ETag", "\"v2\"")], "changed"));
+        seed(
+            &format!("{}/tool.zip", server.url),
+            "\"v1\"",
+            Some("cached"),
+            None,
+        );
+
+        let (body, entry) = download(&server);
+        assert_eq!(body, "changed");
+        assert_eq!(fs::read_to_string(&entry.path).unwrap(), "changed");
+        assert_eq!(entry.meta().unwrap().etag.as_deref(), Some("\"v2\""));
+    }
+
+    #[test]
+    fn resumes_a_partial_download() {
+        let server = TestServer::start(|request| {
+            match (request.header("Range"), request.header("If-Range")) {
+                (Some("bytes=6-"), Some("\"v1\"")) => reply(
+                    206,
+                    &[("ETag", "\"v1\""), ("Content-Range", "bytes 6-10/11")],
+                    "world",
+                ),
+                _ => reply(200, &[("ETag", "\"v1\"")], "unexpected full download"),
+            }
+        });
+        seed(
+            &format!("{}/tool.zip", server.url),
+            "\"v1\"",
+            None,
+            Some("hello "),
+        );
+
+        let (body, entry) = download(&server);
+        assert_eq!(body, "hello world");
+        assert!(!entry.part_path().exists());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn starts_over_when_the_server_sends_the_whole_file() {
+        // What a server does when If-Range no longer matches
+        let server = TestServer::start(|_| reply(200, &[("ETag", "\"v2\"")], "hello world"));
+        seed(
+            &format!("{}/tool.zip", server.url),
+            "\"v1\"",
+            None,
+            Some("stale "),
+        );
+
+        let (body, entry) = download(&server);
+        assert_eq!(body, "hello world");
+        assert_eq!(entry.meta().unwrap().etag.as_deref(), Some("\"v2\""));
+    }
+
+    #[test]
+    fn discards_a_partial_download_the_server_cannot_resume() {
+        let server = TestServer::start(|request| match request.header("Range") {
+            Some(_) => reply(416, &[], ""),
+            None => reply(200, &[("ETag", "\"v1\"")], "hello world"),
+        });
+        seed(
+            &format!("{}/tool.zip", server.url),
+            "\"v1\"",
+            None,
+            Some("far too long to resume"),
+        );
+
+        let (body, entry) = download(&server);
+        assert_eq!(body, "hello world");
+        assert!(!entry.part_path().exists());
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn resumes_an_interrupted_download_against_the_new_etag() {
+        let count = AtomicUsize::new(0);
+        let server = TestServer::start(move |request| {
+            if count.fetch_add(1, Ordering::SeqCst) == 0 {
+                // Promises 11 bytes, then hangs up after 5
+                return "HTTP/1.1 200 Test\r\nConnection: close\r\nContent-Length: 11\r\nETag: \"v2\"\r\n\r\nhello".to_string();
+            }
+            match (request.header("Range"), request.header("If-Range")) {
+                (Some("bytes=5-"), Some("\"v2\"")) => reply(
+                    206,
+                    &[("ETag", "\"v2\""), ("Content-Range", "bytes 5-10/11")],
+                    " world",
+                ),
+                _ => reply(200, &[("ETag", "\"v2\"")], "resumed against the wrong copy"),
+            }
+        });
+        // The old copy must not be kept, or revalidated against "v2"
+        seed(
+            &format!("{}/tool.zip", server.url),
+            "\"v1\"",
+            Some("old"),
+            None,
+        );
+
+        let (body, entry) = download(&server);
+        assert_eq!(body, "hello world");
+        assert_eq!(entry.meta().unwrap().etag.as_deref(), Some("\"v2\""));
+        assert_eq!(server.requests().len(), 2);
+    }
+}
@@ -7,6 +7,18 @@ use which::which;
 
 use super::EditorPlugin;
 use super::utils::is_process_running;
+use crate::bundle;
+use crate::download::{self, Artifact};
+use crate::plan::Action;
+
+const PLUGIN_ID: &str = "com.wakatime.intellij.plugin";
+
+/// The plugin's zip, for installing from a bundle. The Marketplace publishes
+/// no checksum for it, so only bundles download it, pinning its digest.
+pub const ARTIFACT: Artifact = Artifact::Unchecked {
+    name: "com.wakatime.intellij.plugin.zip",
+    url: "https://plugins.jetbrains.com/pluginManager?action=download&id=com.wakatime.intellij.plugin",
+};
 
 pub struct JetBrainsFamily {
     pub name: &'static str,
@@ -51,6 +63,51 @@ impl JetBrainsFamily {
         dirs
     }
 
+    /// Where each installed version keeps its plugins. On Linux they live
+    /// under the data dir rather than the config dir.
+    fn plugin_dirs(&self) -> Vec<PathBuf> {
+        #[cfg(target_os = "linux")]
+        {
+            let mut dirs = Vec::new();
+            if let Some(base) = dirs::data_dir().map(|d| d.join("JetBrains"))
+                && let Ok(entries) = std::fs::read_dir(base)
+            {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    if self.product_codes.iter().any(|code| name.starts_with(code)) {
+                        dirs.push(path);
+                    }
+                }
+            }
+            dirs
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            self.config_dirs()
+                .into_iter()
+                .map(|d| d.join("plugins"))
+                .collect()
+        }
+    }
+
+    fn installed_plugin_paths(&self) -> Vec<PathBuf> {
+        self.plugin_dirs()
+            .into_iter()
+            .filter_map(|d| std::fs::read_dir(d).ok())
+            .flat_map(|entries| entries.flatten())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_lowercase()
+                    .starts_with("wakatime")
+            })
+            .collect()
+    }
+
     fn get_fallback_paths(&self) -> Vec<PathBuf> {
         let mut paths = Vec::new();
 
@@ -121,6 +178,54 @@ impl JetBrainsFamily {
     fn is_running(&self) -> bool {
         is_process_running(self.cli_command)
     }
+
+    /// Where to unzip the plugin from a bundle. `installPlugins` can only
+    /// fetch from the Marketplace, so a bundle puts the files there itself.
+    fn bundle_plugin_dirs(&self) -> Result<Vec<PathBuf>> {
+        let dirs = self.plugin_dirs();
+        if dirs.is_empty() {
+            return Err(eyre!(
+                "{} hasn't been opened yet, so it has no plugins folder. Open it once, then run setup again.",
+                self.name
+            ));
+        }
+        Ok(dirs)
+    }
+
+    fn install_from_bundle(&self) -> Result<()> {
+        let dirs = self.bundle_plugin_dirs()?;
+        let plugin = download::fetch(&ARTIFACT)?;
+        let mut zip = zip::ZipArchive::new(std::io::Cursor::new(plugin.bytes))
+            .map_err(|e| eyre!("The {} plugin isn't a valid zip: {}", self.name, e))?;
+        for dir in dirs {
+            zip.extract(&dir)
+                .map_err(|e| eyre!("Failed to unzip the plugin into {}: {}", dir.display(), e))?;
+        }
+        Ok(())
+    }
+
+    fn install_command(&self) -> Result<Command> {
+        let cli_path = self
+            .find_cli()
+            .ok_or_else(|| eyre!("{} CLI not found", self.name))?;
+
+        let mut cmd;
+
+        #[cfg(target_os = "windows")]
+        {
+            cmd = Command::new("cmd");
+            cmd.arg("/C");
+            cmd.arg(&cli_path);
+        }
+
+        #[cfg(not(target_os = "windows"))]
+        {
+            cmd = Command::new(&cli_path);
+        }
+
+        cmd.args(["installPlugins", PLUGIN_ID]);
+        Ok(cmd)
+    }
 }
 
 impl EditorPlugin for JetBrainsFamily {
@@ -132,6 +237,17 @@ impl EditorPlugin for JetBrainsFamily {
         !self.config_dirs().is_empty() || self.find_cli().is_some()
     }
 
+    fn plan(&self) -> Result<Vec<Action>> {
+        if bundle::active().is_none() {
+            return Ok(vec![Action::from_command(&self.install_command()?)]);
+        }
+        Ok(self
+            .bundle_plugin_dirs()?
+            .into_iter()
+            .flat_map(|dir| ARTIFACT.plan(dir))
+            .collect())
+    }
+
     fn install(&self) -> Result<()> {
         if self.is_running() {
             eprintln!(
@@ -143,26 +259,12 @@ impl EditorPlugin for JetBrainsFamily {
             );
         }
 
-        let cli_path = self
-            .find_cli()
-            .ok_or_else(|| eyre!("{} CLI not found", self.name))?;
-
-        let mut cmd;
-
-        #[cfg(target_os = "windows")]
-        {
-            cmd = Command::new("cmd");
-            cmd.arg("/C");
-            cmd.arg(&cli_path);
+        if bundle::active().is_some() {
+            return self.install_from_bundle();
         }
 
-        #[cfg(not(target_os = "windows"))]
-        {
-            cmd = Command::new(&cli_path);
-        }
-
-        let status = cmd
-            .args(["installPlugins", "com.wakatime.intellij.plugin"])
+        let status = self
+            .install_command()?
             .stdout(std::process::Stdio::null())
             .stderr(std::process::Stdio::null())
             .status()?;
@@ -173,4 +275,31 @@ impl EditorPlugin for JetBrainsFamily {
             Err(eyre!("Failed to install WakaTime plugin for {}", self.name))
         }
     }
+
+    fn is_plugin_installed(&self) -> bool {
+        !self.installed_plugin_paths().is_empty()
+    }
+
+    fn uninstall(&self) -> Result<()> {
+        if self.is_running() {
+            eprintln!(
+                "{}",
+                format!(
+                    "Warning: {} appears to be running. Restart it after uninstalling.",
+                    self.name
+                )
+                .yellow()
+            );
+        }
+
+        for path in self.installed_plugin_paths() {
+            let result = if path.is_dir() {
+                std::fs::remove_dir_all(&path)
+            } else {
+                std::fs::remove_file(&path)
+            };
+            result.map_err(|e| eyre!("Failed to remove {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
 }
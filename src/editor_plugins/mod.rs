@@ -6,6 +6,9 @@ mod zed;
 
 use color_eyre::Result;
 
+use crate::download::Artifact;
+use crate::plan::Action;
+
 pub use jetbrains::JetBrainsFamily;
 pub use vscode::VsCodeFamily;
 pub use xcode::Xcode;
@@ -15,11 +18,29 @@ pub trait EditorPlugin: Send + Sync {
     /// Human-readable name, e.g. "VS Code", "Cursor"
     fn name(&self) -> String;
 
+    /// Short identifier used on the command line, e.g. "vscode", "cursor"
+    fn id(&self) -> String {
+        self.name()
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_lowercase()
+    }
+
     /// Detect whether this editor is installed
     fn is_installed(&self) -> bool;
 
+    /// Describe what `install` would do, without doing it
+    fn plan(&self) -> Result<Vec<Action>>;
+
     /// Install the WakaTime plugin for this editor
     fn install(&self) -> Result<()>;
+
+    /// Detect whether the WakaTime plugin is already installed
+    fn is_plugin_installed(&self) -> bool;
+
+    /// Remove the WakaTime plugin from this editor
+    fn uninstall(&self) -> Result<()>;
 }
 
 pub fn all_editors() -> Vec<Box<dyn EditorPlugin>> {
@@ -146,3 +167,9 @@ pub fn all_editors() -> Vec<Box<dyn EditorPlugin>> {
         }),
     ]
 }
+
+/// The plugin downloads a bundle carries. Zed installs its extension from
+/// its own registry, so it can't be bundled.
+pub fn bundle_artifacts() -> Vec<Artifact> {
+    vec![vscode::ARTIFACT, jetbrains::ARTIFACT, xcode::ARTIFACT]
+}
@@ -5,6 +5,17 @@ use color_eyre::{Result, eyre::eyre};
 use which::which;
 
 use super::EditorPlugin;
+use crate::bundle;
+use crate::download::{self, Artifact};
+use crate::plan::Action;
+
+const EXTENSION_ID: &str = "WakaTime.vscode-wakatime";
+
+/// The extension's VSIX, for installing from a bundle.
+pub const ARTIFACT: Artifact = Artifact::OpenVsx {
+    namespace: "WakaTime",
+    extension: "vscode-wakatime",
+};
 
 pub struct VsCodeFamily {
     pub name: &'static str,
@@ -94,23 +105,8 @@ impl VsCodeFamily {
             .into_iter()
             .find(|path| path.exists())
     }
-}
-
-impl EditorPlugin for VsCodeFamily {
-    fn name(&self) -> String {
-        self.name.to_string()
-    }
-
-    fn is_installed(&self) -> bool {
-        // It's installed if we can find the CLI OR the extension folder exists
-        self.find_cli().is_some()
-            || self
-                .extensions_dir()
-                .and_then(|d| d.parent().map(Path::exists))
-                .unwrap_or(false)
-    }
 
-    fn install(&self) -> Result<()> {
+    fn cli_command(&self, args: &[&str]) -> Result<Command> {
         let cli_path = self.find_cli().ok_or_else(|| {
             eyre!(
                 "{} CLI not found. Is it installed and in your PATH?",
@@ -138,12 +134,59 @@ impl EditorPlugin for VsCodeFamily {
             cmd = Command::new(&cli_path);
         }
 
+        cmd.args(args);
+        Ok(cmd)
+    }
+}
+
+impl EditorPlugin for VsCodeFamily {
+    fn name(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn is_installed(&self) -> bool {
+        // It's installed if we can find the CLI OR the extension folder exists
+        self.find_cli().is_some()
+            || self
+                .extensions_dir()
+                .and_then(|d| d.parent().map(Path::exists))
+                .unwrap_or(false)
+    }
+
+    fn plan(&self) -> Result<Vec<Action>> {
+        if bundle::active().is_none() {
+            return Ok(vec![Action::from_command(
+                &self.cli_command(&["--install-extension", EXTENSION_ID])?,
+            )]);
+        }
+
+        let vsix = PathBuf::from("$TMPDIR").join(ARTIFACT.name());
+        let mut actions = ARTIFACT.plan(&vsix);
+        actions.push(Action::from_command(
+            &self.cli_command(&["--install-extension", &vsix.to_string_lossy()])?,
+        ));
+        Ok(actions)
+    }
+
+    fn install(&self) -> Result<()> {
+        // From a bundle, install the VSIX file instead of asking the marketplace
+        let tmp_dir =
+            tempfile::tempdir().map_err(|e| eyre!("Failed to create temp directory: {}", e))?;
+        let extension = if bundle::active().is_some() {
+            let vsix = tmp_dir.path().join(ARTIFACT.name());
+            std::fs::write(&vsix, download::fetch(&ARTIFACT)?.bytes)
+                .map_err(|e| eyre!("Failed to write {}: {}", vsix.display(), e))?;
+            vsix.to_string_lossy().into_owned()
+        } else {
+            EXTENSION_ID.to_string()
+        };
+
+        let mut cmd = self.cli_command(&["--install-extension", &extension])?;
         let status = cmd
-            .args(["--install-extension", "WakaTime.vscode-wakatime"])
             .stdout(std::process::Stdio::null())
             .stderr(std::process::Stdio::null())
             .status()
-            .map_err(|e| eyre!("Failed to execute {:?}: {}", cli_path, e))?;
+            .map_err(|e| eyre!("Failed to execute {:?}: {}", cmd.get_program(), e))?;
 
         if status.success() {
             Ok(())
@@ -155,4 +198,37 @@ impl EditorPlugin for VsCodeFamily {
             ))
         }
     }
+
+    fn is_plugin_installed(&self) -> bool {
+        self.extensions_dir()
+            .and_then(|d| std::fs::read_dir(d).ok())
+            .is_some_and(|entries| {
+                entries.flatten().any(|entry| {
+                    entry
+                        .file_name()
+                        .to_string_lossy()
+                        .to_lowercase()
+                        .starts_with(&EXTENSION_ID.to_lowercase())
+                })
+            })
+    }
+
+    fn uninstall(&self) -> Result<()> {
+        let mut cmd = self.cli_command(&["--uninstall-extension", EXTENSION_ID])?;
+        let status = cmd
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .map_err(|e| eyre!("Failed to execute {:?}: {}", cmd.get_program(), e))?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(eyre!(
+                "Failed to uninstall WakaTime extension for {}. Exit code: {:?}",
+                self.name,
+                status.code()
+            ))
+        }
+    }
 }
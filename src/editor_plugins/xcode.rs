@@ -1,17 +1,28 @@
+#[cfg(target_os = "macos")]
 use std::fs;
+#[cfg(target_os = "macos")]
 use std::path::PathBuf;
+#[cfg(target_os = "macos")]
 use std::process::Command;
 
 use color_eyre::{Result, eyre::eyre};
 
 use super::EditorPlugin;
+#[cfg(target_os = "macos")]
+use crate::download;
+use crate::download::Artifact;
+use crate::plan::Action;
 
-const DOWNLOAD_URL: &str =
-    "https://github.com/wakatime/macos-wakatime/releases/latest/download/macos-wakatime.zip";
+/// The WakaTime menu bar app, which tracks Xcode.
+pub const ARTIFACT: Artifact = Artifact::GithubRelease {
+    repo: "wakatime/macos-wakatime",
+    asset: "macos-wakatime.zip",
+};
 
 pub struct Xcode;
 
 impl Xcode {
+    #[cfg(target_os = "macos")]
     fn app_path() -> PathBuf {
         PathBuf::from("/Applications/WakaTime.app")
     }
@@ -38,39 +49,63 @@ impl EditorPlugin for Xcode {
         }
     }
 
-    fn install(&self) -> Result<()> {
+    fn plan(&self) -> Result<Vec<Action>> {
+        #[cfg(target_os = "macos")]
+        {
+            if Self::app_path().exists() {
+                return Ok(Vec::new());
+            }
+
+            let tmp_dir = PathBuf::from("$TMPDIR");
+            let zip_path = tmp_dir.join("macos-wakatime.zip");
+            let extracted_app = tmp_dir.join("WakaTime.app");
+            let app_path = Self::app_path().to_string_lossy().into_owned();
+
+            let mut actions = ARTIFACT.plan(zip_path.clone());
+            actions.extend([
+                Action::RunCommand {
+                    program: "ditto".to_string(),
+                    args: vec![
+                        "-xk".to_string(),
+                        zip_path.to_string_lossy().into_owned(),
+                        tmp_dir.to_string_lossy().into_owned(),
+                    ],
+                },
+                Action::RunCommand {
+                    program: "cp".to_string(),
+                    args: vec![
+                        "-R".to_string(),
+                        extracted_app.to_string_lossy().into_owned(),
+                        app_path.clone(),
+                    ],
+                },
+                Action::RunCommand {
+                    program: "open".to_string(),
+                    args: vec![app_path],
+                },
+            ]);
+            Ok(actions)
+        }
+
         #[cfg(not(target_os = "macos"))]
         {
-            return Err(eyre!("Xcode is only supported on macOS"));
+            Err(eyre!("Xcode is only supported on macOS"))
         }
+    }
 
+    fn install(&self) -> Result<()> {
         #[cfg(target_os = "macos")]
         {
             if Self::app_path().exists() {
                 return Ok(());
             }
 
-            let tmp_dir = tempfile::tempdir()
-                .map_err(|e| eyre!("Failed to create temp directory: {}", e))?;
+            let tmp_dir =
+                tempfile::tempdir().map_err(|e| eyre!("Failed to create temp directory: {}", e))?;
             let zip_path = tmp_dir.path().join("macos-wakatime.zip");
 
-            let client = reqwest::blocking::Client::new();
-            let response = client
-                .get(DOWNLOAD_URL)
-                .send()
-                .map_err(|e| eyre!("Failed to download WakaTime for Mac: {}", e))?;
-
-            if !response.status().is_success() {
-                return Err(eyre!(
-                    "Failed to download WakaTime for Mac (HTTP {})",
-                    response.status()
-                ));
-            }
-
-            let bytes = response
-                .bytes()
-                .map_err(|e| eyre!("Failed to read download: {}", e))?;
-            fs::write(&zip_path, &bytes)
+            let archive = download::fetch(&ARTIFACT)?;
+            fs::write(&zip_path, &archive.bytes)
                 .map_err(|e| eyre!("Failed to write zip file: {}", e))?;
 
             let status = Command::new("ditto")
@@ -117,5 +152,44 @@ impl EditorPlugin for Xcode {
 
             Ok(())
         }
+
+        #[cfg(not(target_os = "macos"))]
+        {
+            Err(eyre!("Xcode is only supported on macOS"))
+        }
+    }
+
+    fn is_plugin_installed(&self) -> bool {
+        #[cfg(target_os = "macos")]
+        {
+            Self::app_path().exists()
+        }
+
+        #[cfg(not(target_os = "macos"))]
+        {
+            false
+        }
+    }
+
+    fn uninstall(&self) -> Result<()> {
+        #[cfg(target_os = "macos")]
+        {
+            if !Self::app_path().exists() {
+                return Ok(());
+            }
+
+            // Quit the menu bar app first; it's fine if it wasn't running
+            let _ = Command::new("osascript")
+                .args(["-e", "quit app \"WakaTime\""])
+                .output();
+
+            fs::remove_dir_all(Self::app_path())
+                .map_err(|e| eyre!("Failed to remove WakaTime.app: {}", e))
+        }
+
+        #[cfg(not(target_os = "macos"))]
+        {
+            Err(eyre!("Xcode is only supported on macOS"))
+        }
     }
 }
@@ -8,6 +8,8 @@ use jsonc_parser::{ParseOptions, cst::CstRootNode, json};
 
 use super::EditorPlugin;
 use super::utils::is_process_running;
+use crate::bundle;
+use crate::plan::Action;
 
 pub struct Zed;
 
@@ -26,10 +28,10 @@ impl Zed {
             if let Ok(o) = Command::new("xdg-mime")
                 .args(["query", "default", "x-scheme-handler/zed"])
                 .output()
+                && o.status.success()
+                && !o.stdout.is_empty()
             {
-                if o.status.success() && !o.stdout.is_empty() {
-                    return true;
-                }
+                return true;
             }
             [
                 PathBuf::from("/usr/bin/zed"),
@@ -82,7 +84,56 @@ impl Zed {
         }
     }
 
+    fn settings_path() -> Result<PathBuf> {
+        Ok(Self::config_dir()
+            .ok_or_else(|| eyre!("Could not determine Zed config directory"))?
+            .join("settings.json"))
+    }
+
+    /// Where Zed unpacks installed extensions.
+    fn installed_extension_dir() -> Option<PathBuf> {
+        #[cfg(target_os = "macos")]
+        {
+            dirs::home_dir()
+                .map(|h| h.join("Library/Application Support/Zed/extensions/installed/wakatime"))
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            std::env::var("FLATPAK_XDG_DATA_HOME")
+                .map(PathBuf::from)
+                .ok()
+                .or_else(dirs::data_dir)
+                .map(|d| d.join("zed/extensions/installed/wakatime"))
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            dirs::data_local_dir().map(|d| d.join("Zed/extensions/installed/wakatime"))
+        }
+
+        #[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+        {
+            None
+        }
+    }
+
+    /// Zed only installs extensions from its own registry, so a bundle can't
+    /// stand in for the internet.
+    fn check_not_from_bundle() -> Result<()> {
+        if bundle::active().is_some() {
+            return Err(eyre!(
+                "Zed installs extensions from its own registry, so it can't be installed from a bundle. Run setup with internet to add it."
+            ));
+        }
+        Ok(())
+    }
+
     fn add_extension_to_settings(settings_path: &PathBuf) -> Result<()> {
+        Self::set_auto_install(settings_path, true)
+    }
+
+    fn set_auto_install(settings_path: &PathBuf, enabled: bool) -> Result<()> {
         let content = if settings_path.exists() {
             let s = fs::read_to_string(settings_path)
                 .map_err(|e| eyre!("Failed to read {}: {}", settings_path.display(), e))?;
@@ -108,10 +159,10 @@ impl Zed {
 
         match extensions.get("wakatime") {
             None => {
-                extensions.append("wakatime", json!(true));
+                extensions.append("wakatime", json!(enabled));
             }
             Some(prop) => {
-                prop.set_value(json!(true));
+                prop.set_value(json!(enabled));
             }
         }
 
@@ -134,7 +185,16 @@ impl EditorPlugin for Zed {
         Self::has_url_handler()
     }
 
+    fn plan(&self) -> Result<Vec<Action>> {
+        Self::check_not_from_bundle()?;
+        Ok(vec![Action::WriteFile {
+            path: Self::settings_path()?,
+            description: "set auto_install_extensions.wakatime = true".to_string(),
+        }])
+    }
+
     fn install(&self) -> Result<()> {
+        Self::check_not_from_bundle()?;
         if is_process_running("zed") {
             eprintln!(
                 "{}",
@@ -142,10 +202,26 @@ impl EditorPlugin for Zed {
             );
         }
 
-        let settings_path = Self::config_dir()
-            .ok_or_else(|| eyre!("Could not determine Zed config directory"))?
-            .join("settings.json");
+        Self::add_extension_to_settings(&Self::settings_path()?)
+    }
+
+    fn is_plugin_installed(&self) -> bool {
+        Self::installed_extension_dir().is_some_and(|d| d.exists())
+    }
+
+    fn uninstall(&self) -> Result<()> {
+        let settings_path = Self::settings_path()?;
+        if settings_path.exists() {
+            Self::set_auto_install(&settings_path, false)?;
+        }
 
-        Self::add_extension_to_settings(&settings_path)
+        if let Some(dir) = Self::installed_extension_dir()
+            && dir.exists()
+        {
+            fs::remove_dir_all(&dir)
+                .map_err(|e| eyre!("Failed to remove {}: {}", dir.display(), e))?;
+        }
+
+        Ok(())
     }
 }
@@ -0,0 +1,325 @@
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Local, Utc};
+use color_eyre::{Result, eyre::eyre};
+use ini::Ini;
+use rand::Rng;
+use reqwest::StatusCode;
+use reqwest::blocking::{RequestBuilder, Response};
+use reqwest::header::{DATE, USER_AGENT};
+use serde::{Deserialize, Serialize};
+
+use crate::http;
+
+/// How long to wait for the test heartbeat to show up after it's accepted.
+const RECORD_TIMEOUT: Duration = Duration::from_secs(15);
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Serialize)]
+struct Heartbeat {
+    #[serde(rename = "type")]
+    kind: String,
+    time: u64,
+    entity: String,
+    language: String,
+    project: String,
+    category: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    branch: Option<String>,
+    user_agent: String,
+}
+
+/// The parts of the user's config that change what a real plugin would send.
+pub struct Machine {
+    name: String,
+    hide_branch_names: bool,
+}
+
+impl Machine {
+    /// Reads `hostname` and `hide_branch_names` the way wakatime-cli does,
+    /// falling back to this computer's hostname.
+    pub fn from_config(conf: &Ini) -> Self {
+        let get = |key: &str| {
+            conf.get_from(Some("settings"), key)
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+        };
+
+        Self {
+            name: get("hostname")
+                .map(str::to_string)
+                .unwrap_or_else(system_hostname),
+            // Anything other than an explicit false (including a list of
+            // patterns) may hide this branch, so leave it out to be safe
+            hide_branch_names: get("hide_branch_names")
+                .is_some_and(|v| !v.eq_ignore_ascii_case("false")),
+        }
+    }
+}
+
+fn system_hostname() -> String {
+    Command::new("hostname")
+        .output()
+        .ok()
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown-hostname".to_string())
+}
+
+fn user_agent() -> String {
+    format!(
+        "hackatime-setup/{} ({}-{})",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )
+}
+
+#[derive(Deserialize)]
+struct HeartbeatsResponse {
+    data: Vec<RecordedHeartbeat>,
+}
+
+#[derive(Deserialize)]
+struct RecordedHeartbeat {
+    entity: String,
+}
+
+#[derive(Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Delivery {
+    /// The heartbeat came back when we asked for today's heartbeats.
+    Recorded,
+    /// The server took the heartbeat but it never came back.
+    AcceptedNotRecorded,
+    /// The server took the heartbeat but we couldn't list heartbeats to see
+    /// whether it was kept. See [`RoundTrip::unverified_because`].
+    AcceptedUnverified,
+}
+
+/// What happened to the test heartbeat after it was sent.
+#[derive(Serialize)]
+pub struct RoundTrip {
+    pub entity: String,
+    pub delivery: Delivery,
+    pub http_status: u16,
+    pub latency_ms: u128,
+    pub recorded_after_ms: Option<u128>,
+    pub server_time: Option<DateTime<Utc>>,
+    /// Why listing heartbeats didn't work, for [`Delivery::AcceptedUnverified`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unverified_because: Option<String>,
+}
+
+/// Sends a heartbeat for a file name nobody else will use, then polls today's
+/// heartbeats until it shows up or [`RECORD_TIMEOUT`] passes.
+pub fn send_test_heartbeat(api_key: &str, api_url: &str, machine: &Machine) -> Result<RoundTrip> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let entity = format!(
+        "hackatime-setup-test-{:016x}.txt",
+        rand::rng().random::<u64>()
+    );
+    let heartbeat = Heartbeat {
+        kind: "file".to_string(),
+        time: timestamp,
+        entity: entity.clone(),
+        language: "Text".to_string(),
+        project: "hackatime-setup".to_string(),
+        category: "coding".to_string(),
+        branch: (!machine.hide_branch_names).then(|| "main".to_string()),
+        user_agent: user_agent(),
+    };
+
+    let client = http::client()?;
+    let sent_at = Instant::now();
+    // Not retried: a retry after a timeout could record the heartbeat twice
+    let response = http::send_once(
+        client
+            .post(format!("{api_url}/users/current/heartbeats"))
+            .bearer_auth(api_key)
+            .header(USER_AGENT, user_agent())
+            .header("X-Machine-Name", &machine.name)
+            .json(&vec![heartbeat]),
+    )?;
+    let latency = sent_at.elapsed();
+
+    let status = response.status();
+    let server_time = server_time(&response);
+    if !status.is_success() {
+        let body = response.text().unwrap_or_default();
+        return Err(eyre!("Test heartbeat failed ({}): {}", status, body));
+    }
+
+    let mut round_trip = RoundTrip {
+        entity,
+        delivery: Delivery::AcceptedNotRecorded,
+        http_status: status.as_u16(),
+        latency_ms: latency.as_millis(),
+        recorded_after_ms: None,
+        server_time,
+        unverified_because: None,
+    };
+
+    let url = format!("{api_url}/users/current/heartbeats");
+    let date = Local::now().format("%Y-%m-%d").to_string();
+    while sent_at.elapsed() < RECORD_TIMEOUT {
+        let request = client
+            .get(&url)
+            .query(&[("date", &date)])
+            .bearer_auth(api_key);
+
+        // The heartbeat is already in, so failing to list heartbeats only
+        // means we can't check it
+        match poll(request, &round_trip.entity) {
+            Ok(true) => {
+                round_trip.delivery = Delivery::Recorded;
+                round_trip.recorded_after_ms = Some(sent_at.elapsed().as_millis());
+                break;
+            }
+            Ok(false) => thread::sleep(POLL_INTERVAL),
+            Err(reason) => {
+                round_trip.delivery = Delivery::AcceptedUnverified;
+                round_trip.unverified_because = Some(reason);
+                break;
+            }
+        }
+    }
+
+    Ok(round_trip)
+}
+
+/// Lists today's heartbeats and looks for `entity`. Errors say why the list
+/// couldn't be read.
+fn poll(request: RequestBuilder, entity: &str) -> Result<bool, String> {
+    let response = http::send(request).map_err(|e| format!("listing heartbeats failed: {e}"))?;
+    match response.status() {
+        s if s.is_success() => response
+            .json::<HeartbeatsResponse>()
+            .map(|r| r.data.iter().any(|h| h.entity == entity))
+            .map_err(|_| "the server lists heartbeats in a format I don't know".to_string()),
+        StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED => {
+            Err("the server doesn't list heartbeats".to_string())
+        }
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            Err("this API key can't list heartbeats".to_string())
+        }
+        status => Err(format!("listing heartbeats failed with HTTP {status}")),
+    }
+}
+
+fn server_time(response: &Response) -> Option<DateTime<Utc>> {
+    let date = response.headers().get(DATE)?.to_str().ok()?;
+    DateTime::parse_from_rfc2822(date)
+        .ok()
+        .map(|d| d.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::test_server::{TestServer, reply};
+
+    const JSON: (&str, &str) = ("Content-Type", "application/json");
+
+    fn machine() -> Machine {
+        Machine {
+            name: "test-machine".to_string(),
+            hide_branch_names: false,
+        }
+    }
+
+    /// Takes every heartbeat, and answers listing them with `list`.
+    fn server(list: fn(&str) -> String) -> TestServer {
+        let sent = Arc::new(Mutex::new(String::new()));
+        TestServer::start(move |request| match request.method.as_str() {
+            "POST" => {
+                let heartbeats: Vec<serde_json::Value> =
+                    serde_json::from_str(&request.body).unwrap();
+                *sent.lock().unwrap() = heartbeats[0]["entity"].as_str().unwrap().to_string();
+                reply(202, &[JSON], "{}")
+            }
+            _ => list(&sent.lock().unwrap()),
+        })
+    }
+
+    fn unverified_because(list: fn(&str) -> String) -> String {
+        let server = server(list);
+        let started = Instant::now();
+        let round_trip = send_test_heartbeat("key", &server.url, &machine()).unwrap();
+
+        assert!(round_trip.delivery == Delivery::AcceptedUnverified);
+        assert!(started.elapsed() < RECORD_TIMEOUT);
+        round_trip.unverified_because.unwrap()
+    }
+
+    #[test]
+    fn finds_the_heartbeat_in_the_list() {
+        let server = server(|entity| {
+            reply(
+                200,
+                &[JSON],
+                &format!(r#"{{"data": [{{"entity": "{entity}"}}]}}"#),
+            )
+        });
+        let round_trip = send_test_heartbeat("key", &server.url, &machine()).unwrap();
+
+        assert!(round_trip.delivery == Delivery::Recorded);
+        assert_eq!(round_trip.http_status, 202);
+        assert_eq!(server.requests().len(), 2);
+        assert!(server.requests()[1].starts_with("GET /users/current/heartbeats?date="));
+    }
+
+    #[test]
+    fn a_rejected_list_is_unverified() {
+        let reason = unverified_because(|_| reply(401, &[JSON], r#"{"error": "nope"}"#));
+        assert_eq!(reason, "this API key can't list heartbeats");
+    }
+
+    #[test]
+    fn a_list_in_another_format_is_unverified() {
+        let reason = unverified_because(|_| reply(200, &[], "<html>Welcome!</html>"));
+        assert_eq!(
+            reason,
+            "the server lists heartbeats in a format I don't know"
+        );
+    }
+
+    #[test]
+    fn a_missing_list_is_unverified() {
+        let reason = unverified_because(|_| reply(404, &[], "Not Found"));
+        assert_eq!(reason, "the server doesn't list heartbeats");
+    }
+
+    #[test]
+    fn a_dropped_connection_is_unverified() {
+        let reason = unverified_because(|_| String::new());
+        assert!(
+            reason.starts_with("listing heartbeats failed: "),
+            "{reason}"
+        );
+    }
+
+    #[test]
+    fn a_rejected_heartbeat_fails() {
+        let server = TestServer::start(|_| reply(400, &[JSON], r#"{"error": "bad"}"#));
+        let Err(error) = send_test_heartbeat("key", &server.url, &machine()) else {
+            panic!("a rejected heartbeat was reported as sent");
+        };
+        assert!(error.to_string().contains("400"), "{error}");
+    }
+
+    #[test]
+    fn a_failed_heartbeat_is_not_sent_again() {
+        let server = TestServer::start(|_| reply(503, &[], "busy"));
+        assert!(send_test_heartbeat("key", &server.url, &machine()).is_err());
+        assert_eq!(server.requests(), ["POST /users/current/heartbeats"]);
+    }
+}
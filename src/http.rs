@@ -0,0 +1,403 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use color_eyre::Result;
+use colored::Colorize;
+use reqwest::blocking::{Client, ClientBuilder, RequestBuilder, Response};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::redirect::Policy;
+use reqwest::{Certificate, NoProxy, Proxy, StatusCode};
+
+use crate::output::{self, ErrorCode};
+use crate::{NetworkArgs, config};
+
+/// The network settings wakatime-cli reads from `[settings]`, which setup
+/// needs to honor too: school networks often only work through a proxy that
+/// intercepts TLS.
+#[derive(Clone, Default)]
+pub struct NetworkSettings {
+    pub proxy: Option<String>,
+    pub ssl_certs_file: Option<PathBuf>,
+    pub no_ssl_verify: bool,
+    pub timeouts: Timeouts,
+}
+
+/// How long to wait and how often to retry, from `--connect-timeout`,
+/// `--timeout` and `--retries`.
+#[derive(Clone, Copy)]
+pub struct Timeouts {
+    pub connect: Duration,
+    pub request: Duration,
+    pub retries: u32,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: Duration::from_secs(10),
+            request: Duration::from_secs(30),
+            retries: 3,
+        }
+    }
+}
+
+/// The first retry waits this long, doubling after each attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// Never wait longer than this between attempts, whatever `Retry-After` says.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+impl NetworkSettings {
+    /// Reads the settings from the config at `config_path`, if there is one,
+    /// with any flags given on the command line taking precedence.
+    pub fn load(config_path: &Path, args: &NetworkArgs) -> Self {
+        let conf = fs::read_to_string(config_path)
+            .ok()
+            .and_then(|contents| config::parse_config(&contents).ok());
+        let get = |key: &str| {
+            conf.as_ref()
+                .and_then(|c| c.get_from(Some("settings"), key))
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+        };
+
+        Self {
+            proxy: args.proxy.clone().or(get("proxy").map(str::to_string)),
+            ssl_certs_file: args
+                .ssl_certs_file
+                .clone()
+                .or(get("ssl_certs_file").map(PathBuf::from)),
+            no_ssl_verify: args.no_ssl_verify || get("no_ssl_verify") == Some("true"),
+            timeouts: Timeouts {
+                connect: Duration::from_secs(args.connect_timeout),
+                request: Duration::from_secs(args.timeout),
+                retries: args.retries,
+            },
+        }
+    }
+
+    /// The proxy and CA bundle keys to write so editor plugins use the same
+    /// network setup. `no_ssl_verify` is left out: setup only writes it when
+    /// someone explicitly agrees to.
+    pub fn config_settings(&self) -> Vec<(String, String)> {
+        let mut settings = Vec::new();
+        if let Some(proxy) = &self.proxy {
+            settings.push(("proxy".to_string(), proxy.clone()));
+        }
+        if let Some(path) = &self.ssl_certs_file {
+            settings.push(("ssl_certs_file".to_string(), path.display().to_string()));
+        }
+        settings
+    }
+}
+
+struct Network {
+    settings: NetworkSettings,
+    client: Client,
+    client_without_redirects: Client,
+}
+
+impl Network {
+    fn new(
+        settings: NetworkSettings,
+        certificates: &[Certificate],
+    ) -> Result<Self, reqwest::Error> {
+        let builder = || -> Result<ClientBuilder, reqwest::Error> {
+            let mut builder = Client::builder()
+                .connect_timeout(settings.timeouts.connect)
+                .timeout(settings.timeouts.request)
+                .danger_accept_invalid_certs(settings.no_ssl_verify);
+            if let Some(proxy) = &settings.proxy {
+                builder = builder.proxy(Proxy::all(proxy)?.no_proxy(NoProxy::from_env()));
+            }
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate.clone());
+            }
+            Ok(builder)
+        };
+
+        Ok(Self {
+            client: builder()?.build()?,
+            client_without_redirects: builder()?.redirect(Policy::none()).build()?,
+            settings,
+        })
+    }
+}
+
+static NETWORK: Mutex<Option<Network>> = Mutex::new(None);
+
+/// Builds the clients every later request uses from `settings`. Fails if the
+/// CA bundle can't be read or the proxy URL is invalid.
+///
+/// Only commands that make requests call this, so a bad setting never stops
+/// `config set` or `restore` from fixing it.
+pub fn configure(settings: &NetworkSettings) -> Result<()> {
+    if let Some(proxy) = &settings.proxy {
+        Proxy::all(proxy).map_err(|e| {
+            output::coded(
+                ErrorCode::NetworkConfigInvalid,
+                format!("Invalid proxy \"{proxy}\": {e}"),
+            )
+        })?;
+    }
+
+    let certificates = match &settings.ssl_certs_file {
+        Some(path) => {
+            let pem = fs::read(path).map_err(|e| {
+                output::coded(
+                    ErrorCode::NetworkConfigInvalid,
+                    format!(
+                        "Failed to read CA certificates from {}: {}",
+                        config::display_path(path),
+                        e
+                    ),
+                )
+            })?;
+            Certificate::from_pem_bundle(&pem).map_err(|e| {
+                output::coded(
+                    ErrorCode::NetworkConfigInvalid,
+                    format!(
+                        "{} isn't a PEM certificate bundle: {}",
+                        config::display_path(path),
+                        e
+                    ),
+                )
+            })?
+        }
+        None => Vec::new(),
+    };
+
+    *NETWORK.lock().unwrap() = Some(Network::new(settings.clone(), &certificates)?);
+    Ok(())
+}
+
+/// Runs `f` with the configured network, falling back to the defaults when
+/// [`configure`] hasn't been called.
+fn with_network<T>(f: impl FnOnce(&Network) -> T) -> Result<T, reqwest::Error> {
+    let mut network = NETWORK.lock().unwrap();
+    if network.is_none() {
+        *network = Some(Network::new(NetworkSettings::default(), &[])?);
+    }
+    Ok(f(network.as_ref().unwrap()))
+}
+
+/// A client for every request setup makes. Without a configured proxy,
+/// reqwest picks up `HTTPS_PROXY`, `HTTP_PROXY` and `NO_PROXY` itself.
+pub fn client() -> Result<Client, reqwest::Error> {
+    with_network(|n| n.client.clone())
+}
+
+/// Like [`client`], but hands back redirects instead of following them.
+pub fn client_without_redirects() -> Result<Client, reqwest::Error> {
+    with_network(|n| n.client_without_redirects.clone())
+}
+
+/// The settings passed to the last [`configure`].
+pub fn settings() -> NetworkSettings {
+    NETWORK
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|n| n.settings.clone())
+        .unwrap_or_default()
+}
+
+/// Sends `request`, retrying with exponential backoff when the connection
+/// fails or times out, or the server answers 429 or 5xx. A `Retry-After`
+/// header sets the wait instead. After the last retry the final response (or
+/// error) is returned as is.
+pub fn send(request: RequestBuilder) -> Result<Response, reqwest::Error> {
+    send_with_retries(request, settings().timeouts.retries, INITIAL_BACKOFF)
+}
+
+/// Sends `request` once, for requests that aren't safe to repeat: a timeout
+/// can come after the server has already acted on them.
+pub fn send_once(request: RequestBuilder) -> Result<Response, reqwest::Error> {
+    request.send()
+}
+
+fn send_with_retries(
+    request: RequestBuilder,
+    retries: u32,
+    mut backoff: Duration,
+) -> Result<Response, reqwest::Error> {
+    for attempt in 1.. {
+        // Bodies we send are always in memory, so this only fails for streams
+        let Some(this_attempt) = request.try_clone() else {
+            return request.send();
+        };
+        let result = this_attempt.send();
+
+        let (reason, wait) = match &result {
+            Ok(r) if is_retryable(r.status()) => (
+                format!("The server answered {}", r.status()),
+                retry_after(r.headers()).unwrap_or(backoff),
+            ),
+            Err(e) if e.is_connect() || e.is_timeout() => (
+                if e.is_timeout() {
+                    "The request timed out".to_string()
+                } else {
+                    "Couldn't connect".to_string()
+                },
+                backoff,
+            ),
+            _ => return result,
+        };
+        if attempt > retries {
+            return result;
+        }
+
+        let wait = wait.min(MAX_BACKOFF);
+        eprintln!(
+            "{}",
+            format!(
+                "{reason}, retrying in {:.1}s (attempt {} of {})...",
+                wait.as_secs_f32(),
+                attempt + 1,
+                retries + 1
+            )
+            .dimmed()
+        );
+        thread::sleep(wait);
+        backoff = backoff.saturating_mul(2).min(MAX_BACKOFF);
+    }
+
+    unreachable!()
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Reads `Retry-After` as either a number of seconds or an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    (at.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+    use crate::test_server::{TestServer, refused_url, reply};
+
+    const BACKOFF: Duration = Duration::from_millis(10);
+
+    /// Answers with `statuses` in order, repeating the last one.
+    fn server(statuses: &[u16], headers: &'static [(&str, &str)]) -> TestServer {
+        let statuses = statuses.to_vec();
+        let count = AtomicUsize::new(0);
+        TestServer::start(move |_| {
+            let i = count.fetch_add(1, Ordering::SeqCst).min(statuses.len() - 1);
+            reply(statuses[i], headers, "")
+        })
+    }
+
+    fn get(server: &TestServer, retries: u32) -> Result<Response, reqwest::Error> {
+        send_with_retries(client().unwrap().get(&server.url), retries, BACKOFF)
+    }
+
+    fn header(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn retries_busy_servers_until_they_answer() {
+        let server = server(&[429, 503, 200], &[]);
+        let started = Instant::now();
+
+        assert_eq!(get(&server, 3).unwrap().status(), StatusCode::OK);
+        assert_eq!(server.requests().len(), 3);
+        // 10ms, then double that
+        assert!(started.elapsed() >= BACKOFF * 3);
+    }
+
+    #[test]
+    fn returns_the_last_response_after_the_last_retry() {
+        let server = server(&[500], &[]);
+
+        assert_eq!(
+            get(&server, 2).unwrap().status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn does_not_retry_client_errors() {
+        for status in [400, 401, 404] {
+            let server = server(&[status, 200], &[]);
+
+            assert_eq!(get(&server, 3).unwrap().status().as_u16(), status);
+            assert_eq!(server.requests().len(), 1);
+        }
+    }
+
+    #[test]
+    fn does_not_retry_without_retries() {
+        let server = server(&[503, 200], &[]);
+
+        assert_eq!(
+            get(&server, 0).unwrap().status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn waits_as_long_as_retry_after_asks() {
+        let server = server(&[429, 200], &[("Retry-After", "1")]);
+        let started = Instant::now();
+
+        assert_eq!(get(&server, 1).unwrap().status(), StatusCode::OK);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn retries_failed_connections() {
+        let started = Instant::now();
+        let request = client().unwrap().get(refused_url());
+
+        let error = send_with_retries(request, 2, BACKOFF).unwrap_err();
+        assert!(error.is_connect());
+        assert!(started.elapsed() >= BACKOFF * 3);
+    }
+
+    #[test]
+    fn reads_retry_after_in_seconds() {
+        assert_eq!(retry_after(&header("120")), Some(Duration::from_secs(120)));
+        assert_eq!(retry_after(&header(" 0 ")), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn reads_retry_after_as_a_date() {
+        let at = Utc::now() + chrono::Duration::seconds(90);
+        let wait = retry_after(&header(&at.to_rfc2822())).unwrap();
+        assert!(wait > Duration::from_secs(85) && wait <= Duration::from_secs(90));
+
+        let gmt = at.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        assert!(retry_after(&header(&gmt)).is_some());
+    }
+
+    #[test]
+    fn ignores_past_or_unreadable_retry_after() {
+        let past = Utc::now() - chrono::Duration::seconds(90);
+        assert_eq!(retry_after(&header(&past.to_rfc2822())), None);
+        assert_eq!(retry_after(&header("soon")), None);
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt;
+use std::sync::{LazyLock, OnceLock};
+
+use clap::ValueEnum;
+use color_eyre::Report;
+use indicatif::{MultiProgress, ProgressBar};
+use serde::Serialize;
+use serde_json::{Value, json};
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Colored text for people
+    #[default]
+    Human,
+    /// One JSON document on stdout for scripts
+    Json,
+}
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+pub fn set_format(format: OutputFormat) {
+    let _ = FORMAT.set(format);
+}
+
+pub fn is_json() -> bool {
+    FORMAT.get() == Some(&OutputFormat::Json)
+}
+
+/// `println!` that stays quiet in JSON mode, so stdout only ever holds the
+/// JSON document. Warnings go to stderr and don't need this.
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if !$crate::output::is_json() {
+            println!($($arg)*);
+        }
+    };
+}
+pub(crate) use say;
+
+static PROGRESS: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
+
+/// Shows `bar` on stderr, or nothing in JSON mode. Bars share one display,
+/// so a download's bar can sit under the spinner of the step that started it.
+pub fn progress_bar(bar: ProgressBar) -> ProgressBar {
+    if is_json() {
+        ProgressBar::hidden()
+    } else {
+        PROGRESS.add(bar)
+    }
+}
+
+/// Stable error codes for JSON output. Renaming one is a breaking change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    MissingApiKey,
+    InvalidApiKey,
+    ApiKeyRejected,
+    InvalidApiUrl,
+    NoTerminal,
+    UnknownEditor,
+    AnswersInvalid,
+    ConfigNotFound,
+    ConfigInvalid,
+    NetworkConfigInvalid,
+    KeyNotSet,
+    PluginInstallFailed,
+    PluginUninstallFailed,
+    WakatimeCliInstallFailed,
+    ChecksumMismatch,
+    BundleCreateFailed,
+    BundleInvalid,
+    HeartbeatFailed,
+    HeartbeatNotRecorded,
+    Internal,
+}
+
+/// An error we expect users to hit, with a code for scripts and a message
+/// that's shown without a backtrace.
+#[derive(Debug)]
+pub struct CodedError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl fmt::Display for CodedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for CodedError {}
+
+pub fn coded(code: ErrorCode, message: impl Into<String>) -> Report {
+    CodedError {
+        code,
+        message: message.into(),
+    }
+    .into()
+}
+
+#[derive(Serialize)]
+pub struct ErrorInfo {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl ErrorInfo {
+    pub fn new(code: ErrorCode, message: impl fmt::Display) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+
+    /// Uses the report's own code when it has one, `fallback` otherwise.
+    pub fn from_report(report: &Report, fallback: ErrorCode) -> Self {
+        match report.downcast_ref::<CodedError>() {
+            Some(e) => Self::new(e.code, &e.message),
+            None => Self::new(fallback, report),
+        }
+    }
+}
+
+/// The result of one step, e.g. installing one plugin.
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Outcome {
+    Ok,
+    Skipped,
+    Failed { error: ErrorInfo },
+}
+
+/// Prints the JSON document for a finished command.
+pub fn emit(command: &str, report: impl Serialize) {
+    let mut document = json!({ "command": command, "ok": true });
+    if let Value::Object(fields) = serde_json::to_value(report).unwrap_or(Value::Null) {
+        document.as_object_mut().unwrap().extend(fields);
+    }
+    println!("{}", serde_json::to_string_pretty(&document).unwrap());
+}
+
+/// Prints the JSON document for a command that failed.
+pub fn emit_error(command: &str, report: &Report) {
+    let document = json!({
+        "command": command,
+        "ok": false,
+        "error": ErrorInfo::from_report(report, ErrorCode::Internal),
+    });
+    println!("{}", serde_json::to_string_pretty(&document).unwrap());
+}
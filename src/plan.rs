@@ -0,0 +1,101 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Command;
+
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::config;
+
+/// A side effect setup would have. Used by `--dry-run` to describe what would
+/// happen without doing it.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    RunCommand {
+        program: String,
+        args: Vec<String>,
+    },
+    WriteFile {
+        path: PathBuf,
+        description: String,
+    },
+    Download {
+        url: String,
+        destination: PathBuf,
+    },
+    HttpRequest {
+        method: &'static str,
+        url: String,
+        description: String,
+    },
+    FromBundle {
+        bundle: PathBuf,
+        file: String,
+        destination: PathBuf,
+    },
+}
+
+impl Action {
+    /// Describes running `cmd` exactly as it's been built.
+    pub fn from_command(cmd: &Command) -> Self {
+        Action::RunCommand {
+            program: cmd.get_program().to_string_lossy().into_owned(),
+            args: cmd
+                .get_args()
+                .map(|a| a.to_string_lossy().into_owned())
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::RunCommand { program, args } => {
+                write!(f, "run {} {}", program, args.join(" "))
+            }
+            Action::WriteFile { path, description } => {
+                write!(f, "write {}: {}", config::display_path(path), description)
+            }
+            Action::Download { url, destination } => {
+                write!(
+                    f,
+                    "download {} to {}",
+                    url,
+                    config::display_path(destination)
+                )
+            }
+            Action::HttpRequest {
+                method,
+                url,
+                description,
+            } => write!(f, "{method} {url} ({description})"),
+            Action::FromBundle {
+                bundle,
+                file,
+                destination,
+            } => write!(
+                f,
+                "copy {} from {} to {}",
+                file,
+                config::display_path(bundle),
+                config::display_path(destination)
+            ),
+        }
+    }
+}
+
+/// Prints one titled group of a dry-run plan.
+pub fn print_step(title: &str, actions: Result<&[Action], &color_eyre::Report>) {
+    println!("{}", title.bold());
+    match actions {
+        Ok([]) => println!("  {}", "nothing to do".dimmed()),
+        Ok(actions) => {
+            for action in actions {
+                println!("  {} {}", "•".cyan(), action);
+            }
+        }
+        Err(e) => println!("  {} {}", "✘ would fail:".red(), e),
+    }
+}
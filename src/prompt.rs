@@ -0,0 +1,114 @@
+use std::io::IsTerminal;
+
+use color_eyre::Result;
+use dialoguer::{Confirm, Input, MultiSelect, Password, Select, theme::ColorfulTheme};
+
+use crate::output::{self, ErrorCode};
+
+/// Whether we can show interactive prompts. dialoguer draws on stderr and
+/// reads keys from stdin, falling back to `/dev/tty` when stdin is a pipe
+/// (which it is under `curl | bash`).
+pub fn can_prompt() -> bool {
+    if !std::io::stderr().is_terminal() {
+        return false;
+    }
+    if std::io::stdin().is_terminal() {
+        return true;
+    }
+
+    #[cfg(unix)]
+    {
+        std::fs::File::open("/dev/tty").is_ok()
+    }
+
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+/// Commands with flags that answer their questions check [`can_prompt`]
+/// first and say which ones, so this only has to say a terminal is needed.
+fn ensure_can_prompt(prompt: &str) -> Result<()> {
+    if can_prompt() {
+        Ok(())
+    } else {
+        Err(output::coded(
+            ErrorCode::NoTerminal,
+            format!("No terminal available to ask \"{prompt}\". Run this command from a terminal."),
+        ))
+    }
+}
+
+/// Asks a yes/no question, or returns `default` straight away when
+/// `assume_yes` is set.
+pub fn confirm(prompt: &str, default: bool, assume_yes: bool) -> Result<bool> {
+    if assume_yes {
+        return Ok(default);
+    }
+    ensure_can_prompt(prompt)?;
+    Ok(Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .default(default)
+        .interact()?)
+}
+
+pub fn select<T: std::fmt::Display>(
+    prompt: &str,
+    items: &[T],
+    default: usize,
+    assume_yes: bool,
+) -> Result<usize> {
+    if assume_yes {
+        return Ok(default);
+    }
+    ensure_can_prompt(prompt)?;
+    Ok(Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .items(items)
+        .default(default)
+        .interact()?)
+}
+
+/// Multi-select with everything selected by default.
+pub fn multi_select<T: std::fmt::Display>(
+    prompt: &str,
+    items: &[T],
+    assume_yes: bool,
+) -> Result<Vec<usize>> {
+    if assume_yes {
+        return Ok((0..items.len()).collect());
+    }
+    ensure_can_prompt(prompt)?;
+    let all_selected = vec![true; items.len()];
+    Ok(MultiSelect::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .items(items)
+        .defaults(&all_selected)
+        .interact()?)
+}
+
+/// Asks for a line of text, or returns `default` straight away when
+/// `assume_yes` is set.
+pub fn input(prompt: &str, default: Option<&str>, assume_yes: bool) -> Result<String> {
+    if let (true, Some(default)) = (assume_yes, default) {
+        return Ok(default.to_string());
+    }
+    ensure_can_prompt(prompt)?;
+    let theme = ColorfulTheme::default();
+    let mut input = Input::<String>::with_theme(&theme).with_prompt(prompt);
+    if let Some(default) = default {
+        input = input.default(default.to_string());
+    }
+    Ok(input.interact_text()?.trim().to_string())
+}
+
+/// Asks for a secret without echoing it, re-asking until `validate` passes.
+pub fn password(prompt: &str, validate: impl Fn(&str) -> Result<(), String>) -> Result<String> {
+    ensure_can_prompt(prompt)?;
+    let input = Password::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .validate_with(|input: &String| validate(input.trim()))
+        .interact()?;
+    Ok(input.trim().to_string())
+}
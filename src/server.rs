@@ -0,0 +1,277 @@
+use color_eyre::Result;
+use colored::Colorize;
+use reqwest::StatusCode;
+
+use crate::output::{self, ErrorCode, say};
+use crate::{DEFAULT_API_URL, http};
+
+/// Where wakatime-cli sends heartbeats when the config has no `api_url`.
+pub const WAKATIME_API_URL: &str = "https://api.wakatime.com/api/v1";
+
+/// Servers people commonly point setup at, usable by name with `--api-url`.
+pub const PRESETS: &[(&str, &str)] = &[
+    ("hackatime", DEFAULT_API_URL),
+    ("wakatime", WAKATIME_API_URL),
+    ("wakapi", "https://wakapi.dev/api"),
+    ("local", "http://localhost:3000/api/hackatime/v1"),
+];
+
+/// Where WakaTime-compatible servers mount their API, most likely first.
+const API_PREFIXES: &[&str] = &[
+    "/api/hackatime/v1",
+    "/api/v1",
+    "/api/compat/wakatime/v1",
+    "/api",
+];
+
+/// What people sometimes paste on the end of an API URL.
+const ENDPOINT_SUFFIXES: &[&str] = &["/users/current/heartbeats", "/users/current", "/heartbeats"];
+
+/// Turns a preset name or a URL into the API URL to write to the config.
+/// URLs are tidied up, and anything that isn't one of the presets is probed
+/// to check it really serves the heartbeat API, finding the prefix when it's
+/// missing.
+pub fn resolve_api_url(input: &str) -> Result<String> {
+    if let Some(preset) = preset(input) {
+        return Ok(preset.to_string());
+    }
+
+    let url = normalize(input);
+
+    let base = origin(&url);
+    let given_prefix = &url[base.len()..];
+    let candidates = std::iter::once(given_prefix)
+        .filter(|p| !p.is_empty())
+        .chain(API_PREFIXES.iter().copied().filter(|p| *p != given_prefix));
+
+    for prefix in candidates {
+        let candidate = format!("{base}{prefix}");
+        match probe(&candidate) {
+            Ok(true) => {
+                if candidate != input {
+                    say!("{} {}", "Using API URL".dimmed(), candidate.cyan());
+                }
+                return Ok(candidate);
+            }
+            Ok(false) => continue,
+            // A full API URL can be kept unchecked, but a bare origin has no
+            // API path for wakatime-cli to use
+            Err(e) if !given_prefix.is_empty() => {
+                eprintln!(
+                    "{} Couldn't check {}: {}",
+                    "Warning:".yellow(),
+                    candidate,
+                    e
+                );
+                return Ok(url);
+            }
+            Err(e) => {
+                return Err(output::coded(
+                    ErrorCode::InvalidApiUrl,
+                    format!(
+                        "Couldn't reach {candidate} to find the API on {input}: {e}. Pass the full API URL (e.g. {DEFAULT_API_URL}) to use it without checking."
+                    ),
+                ));
+            }
+        }
+    }
+
+    Err(output::coded(
+        ErrorCode::InvalidApiUrl,
+        format!(
+            "{input} doesn't look like a WakaTime-compatible server. Pass the full API URL (e.g. {DEFAULT_API_URL}) or one of: {}",
+            PRESETS
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    ))
+}
+
+/// The preset `input` names or points at, which is used without probing.
+fn preset(input: &str) -> Option<&'static str> {
+    let url = normalize(input);
+    PRESETS
+        .iter()
+        .find(|(name, preset)| {
+            name.eq_ignore_ascii_case(input)
+                || preset.trim_end_matches('/') == url
+                || origin(preset) == url
+        })
+        .map(|(_, preset)| *preset)
+}
+
+/// Whether [`resolve_api_url`] probes the server to check `input`.
+pub fn is_probed(input: &str) -> bool {
+    preset(input).is_none()
+}
+
+/// Adds a missing scheme and drops trailing slashes and endpoint paths.
+fn normalize(input: &str) -> String {
+    let mut url = input.trim().to_string();
+    if !url.contains("://") {
+        url = format!("https://{url}");
+    }
+
+    loop {
+        let trimmed = url.trim_end_matches('/');
+        match ENDPOINT_SUFFIXES.iter().find(|s| trimmed.ends_with(*s)) {
+            Some(suffix) => url = trimmed[..trimmed.len() - suffix.len()].to_string(),
+            None => return trimmed.to_string(),
+        }
+    }
+}
+
+/// The scheme, host and port of `url`, without a path.
+fn origin(url: &str) -> &str {
+    let after_scheme = url.find("://").map_or(0, |i| i + 3);
+    match url[after_scheme..].find('/') {
+        Some(i) => &url[..after_scheme + i],
+        None => url,
+    }
+}
+
+/// Whether `api_url` serves `users/current`. Without a key, a real API
+/// answers 401 or 403, or JSON if it lets anyone in. Redirects aren't
+/// followed: a site that sends unknown paths to its home page or a login
+/// page isn't the API.
+fn probe(api_url: &str) -> Result<bool, reqwest::Error> {
+    let request = http::client_without_redirects()?.get(format!("{api_url}/users/current"));
+    let response = http::send(request)?;
+    Ok(match response.status() {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => true,
+        StatusCode::OK => response.json::<serde_json::Value>().is_ok(),
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::CodedError;
+    use crate::test_server::{TestServer, refused_url, reply};
+
+    #[test]
+    fn normalize_adds_a_missing_scheme() {
+        assert_eq!(normalize("example.com"), "https://example.com");
+        assert_eq!(normalize(" example.com/api "), "https://example.com/api");
+        assert_eq!(normalize("http://localhost:3000"), "http://localhost:3000");
+    }
+
+    #[test]
+    fn normalize_drops_trailing_slashes_and_endpoint_paths() {
+        let cases = [
+            ("https://example.com///", "https://example.com"),
+            ("https://example.com/api/v1/", "https://example.com/api/v1"),
+            (
+                "https://example.com/api/v1/users/current/heartbeats",
+                "https://example.com/api/v1",
+            ),
+            (
+                "https://example.com/api/v1/users/current/",
+                "https://example.com/api/v1",
+            ),
+            (
+                "https://example.com/api/heartbeats/users/current/heartbeats/",
+                "https://example.com/api",
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(normalize(input), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn origin_keeps_the_scheme_host_and_port() {
+        assert_eq!(
+            origin("https://example.com:8080/api/v1"),
+            "https://example.com:8080"
+        );
+        assert_eq!(origin("https://example.com"), "https://example.com");
+        assert_eq!(origin("http://localhost:3000/"), "http://localhost:3000");
+    }
+
+    #[test]
+    fn presets_match_by_name_url_or_origin() {
+        let cases = [
+            ("WakaTime", Some(WAKATIME_API_URL)),
+            ("hackatime.hackclub.com", Some(DEFAULT_API_URL)),
+            (
+                "https://hackatime.hackclub.com/api/hackatime/v1/",
+                Some(DEFAULT_API_URL),
+            ),
+            ("https://wakapi.dev", Some("https://wakapi.dev/api")),
+            (
+                "https://wakapi.dev/api/users/current/heartbeats",
+                Some("https://wakapi.dev/api"),
+            ),
+            ("https://wakapi.example.com", None),
+            ("https://wakapi.dev/other", None),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(preset(input), expected, "{input}");
+            assert_eq!(is_probed(input), expected.is_none(), "{input}");
+        }
+    }
+
+    /// Answers 401 to `users/current` under `prefix`, 404 to everything else.
+    fn server(prefix: &'static str) -> TestServer {
+        TestServer::start(move |request| {
+            if request.path == format!("{prefix}/users/current") {
+                reply(401, &[], "")
+            } else {
+                reply(404, &[], "")
+            }
+        })
+    }
+
+    #[test]
+    fn finds_the_api_prefix() {
+        let server = server("/api/compat/wakatime/v1");
+
+        let url = resolve_api_url(&format!("{}/", server.url)).unwrap();
+        assert_eq!(url, format!("{}/api/compat/wakatime/v1", server.url));
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn tries_the_given_prefix_first_and_only_once() {
+        let server = server("/api");
+
+        let url = resolve_api_url(&format!("{}/api/users/current", server.url)).unwrap();
+        assert_eq!(url, format!("{}/api", server.url));
+        assert_eq!(server.requests(), ["GET /api/users/current"]);
+    }
+
+    fn invalid_api_url(input: &str) -> String {
+        let Err(error) = resolve_api_url(input) else {
+            panic!("{input} was accepted");
+        };
+        let coded = error.downcast_ref::<CodedError>().unwrap();
+        assert_eq!(coded.code, ErrorCode::InvalidApiUrl);
+        coded.message.clone()
+    }
+
+    #[test]
+    fn rejects_a_server_without_the_api() {
+        let server = TestServer::start(|_| reply(404, &[], "Not Found"));
+
+        let message = invalid_api_url(&server.url);
+        assert!(message.contains("doesn't look like a WakaTime-compatible server"));
+        assert_eq!(server.requests().len(), API_PREFIXES.len());
+    }
+
+    #[test]
+    fn rejects_an_origin_it_cannot_reach() {
+        let message = invalid_api_url(&refused_url());
+        assert!(message.starts_with("Couldn't reach"), "{message}");
+    }
+
+    #[test]
+    fn keeps_a_full_api_url_it_cannot_reach() {
+        let input = format!("{}/api/v1", refused_url());
+
+        assert_eq!(resolve_api_url(&input).unwrap(), input);
+    }
+}
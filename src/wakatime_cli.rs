@@ -0,0 +1,370 @@
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use color_eyre::{Result, eyre::eyre};
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::download::{self, Artifact};
+use crate::plan::Action;
+use crate::{config, http};
+
+/// Where releases come from unless a mirror is given.
+const RELEASES_URL: &str = "https://github.com/wakatime/wakatime-cli/releases/latest/download";
+
+/// Published with every release: one `<sha256>  <file>` line per asset.
+const CHECKSUMS_FILE: &str = "checksums_sha256.txt";
+
+/// The builds a bundle carries, so it works on every computer at the event.
+const BUNDLE_PLATFORMS: &[&str] = &[
+    "darwin-amd64",
+    "darwin-arm64",
+    "linux-386",
+    "linux-amd64",
+    "linux-arm",
+    "linux-arm64",
+    "windows-386",
+    "windows-amd64",
+    "windows-arm64",
+];
+
+/// Where to download wakatime-cli from: GitHub releases, or a mirror that
+/// serves the same files (`--wakatime-cli-mirror`).
+pub struct Source {
+    base_url: String,
+}
+
+impl Source {
+    pub fn new(mirror: Option<&str>) -> Self {
+        Self {
+            base_url: mirror
+                .unwrap_or(RELEASES_URL)
+                .trim_end_matches('/')
+                .to_string(),
+        }
+    }
+
+    fn url(&self, file: &str) -> String {
+        format!("{}/{}", self.base_url, file)
+    }
+}
+
+/// The wakatime-cli build the plugins will use.
+#[derive(Serialize)]
+pub struct Installed {
+    pub path: PathBuf,
+    pub version: Option<String>,
+    /// Whether this run downloaded it, rather than finding it already there.
+    pub downloaded: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// The `<os>-<arch>` suffix of wakatime-cli's release assets, named the Go way.
+fn platform() -> Result<String> {
+    let os = match std::env::consts::OS {
+        "macos" => "darwin",
+        os @ ("linux" | "windows" | "freebsd" | "openbsd" | "netbsd") => os,
+        os => return Err(eyre!("wakatime-cli has no release for {}", os)),
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "x86" => "386",
+        "arm" => "arm",
+        arch => return Err(eyre!("wakatime-cli has no release for {}", arch)),
+    };
+    Ok(format!("{os}-{arch}"))
+}
+
+/// `wakatime-cli-<os>-<arch>`, the name the plugins look for.
+fn binary_name() -> Result<String> {
+    Ok(format!(
+        "wakatime-cli-{}{}",
+        platform()?,
+        std::env::consts::EXE_SUFFIX
+    ))
+}
+
+/// Where the plugins expect this platform's build.
+pub fn binary_path() -> Result<PathBuf> {
+    Ok(config::wakatime_dir()?.join(binary_name()?))
+}
+
+/// The version the build at `path` reports, if it runs.
+pub fn version(path: &Path) -> Option<String> {
+    let output = Command::new(path).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!version.is_empty()).then_some(version)
+}
+
+/// One wakatime-cli run from [`check`], with what it printed.
+#[derive(Serialize)]
+pub struct CheckRun {
+    pub name: &'static str,
+    pub command: Action,
+    pub ok: bool,
+    pub exit_code: Option<i32>,
+    pub output: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+/// The runs [`check`] makes, heartbeating `entity`.
+fn check_commands(path: &Path, config_path: &Path, entity: &Path) -> Vec<(&'static str, Command)> {
+    let base = || {
+        let mut cmd = Command::new(path);
+        cmd.arg("--config").arg(config_path);
+        cmd
+    };
+
+    let mut config = base();
+    config.args(["--config-read", "api_url"]);
+
+    let mut today = base();
+    today
+        .arg("--today")
+        .arg("--timeout")
+        .arg(http::settings().timeouts.request.as_secs().to_string());
+
+    let mut heartbeat = base();
+    heartbeat
+        .arg("--entity")
+        .arg(entity)
+        .args([
+            "--write",
+            "--project",
+            "hackatime-setup",
+            "--category",
+            "coding",
+        ])
+        .arg("--plugin")
+        .arg(format!("hackatime-setup/{}", env!("CARGO_PKG_VERSION")))
+        .arg("--timeout")
+        .arg(http::settings().timeouts.request.as_secs().to_string());
+
+    vec![
+        ("Read the config", config),
+        ("Fetch today's time", today),
+        ("Send a heartbeat", heartbeat),
+    ]
+}
+
+/// Describes what [`check`] would run.
+pub fn check_plan(path: &Path, config_path: &Path) -> Vec<Action> {
+    let entity = PathBuf::from("$TMPDIR/hackatime-setup-test.txt");
+    check_commands(path, config_path, &entity)
+        .iter()
+        .map(|(_, cmd)| Action::from_command(cmd))
+        .collect()
+}
+
+/// Runs the wakatime-cli at `path` the way an editor would: reading the
+/// config at `config_path`, asking for today's time and sending a heartbeat
+/// for a throwaway file. Unlike our own test heartbeat, this proves the CLI
+/// itself can parse the config and reach `api_url`.
+pub fn check(path: &Path, config_path: &Path, api_url: &str) -> Result<Vec<CheckRun>> {
+    let mut entity = tempfile::Builder::new()
+        .prefix("hackatime-setup-test-")
+        .suffix(".txt")
+        .tempfile()
+        .map_err(|e| eyre!("Failed to create a file to send a heartbeat for: {}", e))?;
+    writeln!(entity, "Written by hackatime_setup to test wakatime-cli.")?;
+
+    let mut runs: Vec<CheckRun> = check_commands(path, config_path, entity.path())
+        .into_iter()
+        .map(|(name, mut cmd)| {
+            let command = Action::from_command(&cmd);
+            let (exit_code, output) = match cmd.output() {
+                Ok(output) => (
+                    output.status.code(),
+                    format!(
+                        "{}{}",
+                        String::from_utf8_lossy(&output.stdout),
+                        String::from_utf8_lossy(&output.stderr)
+                    )
+                    .trim()
+                    .to_string(),
+                ),
+                Err(e) => (None, e.to_string()),
+            };
+            CheckRun {
+                name,
+                command,
+                ok: exit_code == Some(0),
+                exit_code,
+                output,
+                hint: exit_code.and_then(explain_exit_code).map(str::to_string),
+            }
+        })
+        .collect();
+
+    // Reading the config only counts if it finds the api_url we wrote
+    if let Some(read) = runs
+        .first_mut()
+        .filter(|r| r.ok && r.output.trim_end_matches('/') != api_url)
+    {
+        read.ok = false;
+        read.hint = Some(format!(
+            "wakatime-cli reads api_url as \"{}\" instead of {}. Check {} for a second api_url.",
+            read.output,
+            api_url,
+            config::display_path(config_path)
+        ));
+    }
+
+    Ok(runs)
+}
+
+/// Prints a summary of the runs from [`check`].
+pub fn print_checks(runs: &[CheckRun]) {
+    println!("\n{}", "wakatime-cli check:".bold());
+    for run in runs {
+        let icon = if run.ok { "✔".green() } else { "✘".red() };
+        let exit_code = run
+            .exit_code
+            .map_or_else(|| "didn't run".to_string(), |code| format!("exit {code}"));
+        println!("{} {} ({})", icon.bold(), run.name, exit_code.dimmed());
+        for line in run.output.lines() {
+            println!("    {}", line.dimmed());
+        }
+        if let Some(hint) = &run.hint {
+            println!("  {}", hint.yellow());
+        }
+    }
+}
+
+/// What wakatime-cli's exit codes mean, for the ones that aren't success.
+fn explain_exit_code(code: i32) -> Option<&'static str> {
+    Some(match code {
+        0 => return None,
+        102 => {
+            "The server couldn't be reached or returned an error. wakatime-cli saved the heartbeat to send later."
+        }
+        103 => "wakatime-cli couldn't parse the config file.",
+        104 => "The server rejected the API key in the config.",
+        110 => "wakatime-cli couldn't read the config file.",
+        111 => "wakatime-cli couldn't write to the config file.",
+        112 => {
+            "wakatime-cli is backing off after earlier failures and will retry on its own later."
+        }
+        _ => "wakatime-cli failed. Its log is in ~/.wakatime/wakatime.log.",
+    })
+}
+
+/// Describes what [`install`] would do.
+pub fn plan(source: &Source, force: bool) -> Result<Vec<Action>> {
+    let path = binary_path()?;
+    if path.exists() && !force {
+        return Ok(Vec::new());
+    }
+    Ok(artifact(source, &platform()?).plan(path))
+}
+
+/// Downloads this platform's build into the WakaTime folder unless it's
+/// already there (or `force` is set), checking it against the release's
+/// checksums before anything is written.
+pub fn install(source: &Source, force: bool) -> Result<Installed> {
+    let path = binary_path()?;
+    if path.exists() && !force {
+        link(&path)?;
+        return Ok(Installed {
+            version: version(&path),
+            path,
+            downloaded: false,
+            sha256: None,
+        });
+    }
+
+    let archive = download::fetch(&artifact(source, &platform()?))?;
+    let binary = extract(&archive.bytes, &binary_name()?)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| eyre!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    write_executable(&path, &binary)?;
+    link(&path)?;
+
+    Ok(Installed {
+        version: version(&path),
+        path,
+        downloaded: true,
+        sha256: Some(archive.sha256),
+    })
+}
+
+/// The release zip for `platform`.
+fn artifact(source: &Source, platform: &str) -> Artifact {
+    let name = format!("wakatime-cli-{platform}.zip");
+    Artifact::Checksummed {
+        url: source.url(&name),
+        checksums_url: source.url(CHECKSUMS_FILE),
+        name,
+    }
+}
+
+/// The release zips a bundle carries.
+pub fn bundle_artifacts(source: &Source) -> Vec<Artifact> {
+    BUNDLE_PLATFORMS
+        .iter()
+        .map(|platform| artifact(source, platform))
+        .collect()
+}
+
+/// Reads `name` out of the release zip.
+fn extract(archive: &[u8], name: &str) -> Result<Vec<u8>> {
+    let mut zip = zip::ZipArchive::new(Cursor::new(archive))
+        .map_err(|e| eyre!("The wakatime-cli download isn't a valid zip: {}", e))?;
+    let mut file = zip
+        .by_name(name)
+        .map_err(|_| eyre!("{} is missing from the wakatime-cli download", name))?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)
+        .map_err(|e| eyre!("Failed to unzip {}: {}", name, e))?;
+    Ok(contents)
+}
+
+/// Writes next to `path` and renames over it, so a plugin never runs a
+/// half-written binary.
+fn write_executable(path: &Path, contents: &[u8]) -> Result<()> {
+    let partial = path.with_extension("download");
+    fs::write(&partial, contents)
+        .map_err(|e| eyre!("Failed to write {}: {}", partial.display(), e))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&partial, fs::Permissions::from_mode(0o755))
+            .map_err(|e| eyre!("Failed to make {} executable: {}", partial.display(), e))?;
+    }
+
+    fs::rename(&partial, path).map_err(|e| eyre!("Failed to install {}: {}", path.display(), e))
+}
+
+/// Points `wakatime-cli` at the platform build, the way the plugins do. On
+/// Windows they always use the full name, so there's nothing to link.
+fn link(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        let link = path.with_file_name("wakatime-cli");
+        if fs::read_link(&link).is_ok_and(|target| target == path) {
+            return Ok(());
+        }
+        if link.symlink_metadata().is_ok() {
+            fs::remove_file(&link)
+                .map_err(|e| eyre!("Failed to replace {}: {}", link.display(), e))?;
+        }
+        std::os::unix::fs::symlink(path, &link)
+            .map_err(|e| eyre!("Failed to link {}: {}", link.display(), e))?;
+    }
+
+    #[cfg(not(unix))]
+    let _ = path;
+
+    Ok(())
+}